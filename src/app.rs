@@ -1,20 +1,177 @@
-use crate::tree::{self, Index, Tree};
-use egui::{Frame, Ui};
-use std::collections::HashMap;
+use crate::history::{Edit, History};
+use crate::schema::Schema;
+use crate::template::{self, Template};
+use crate::tree::{self, Index, NodeIds, Tooth, Tree};
+use cursor::Cursor;
+use egui::{Key, KeyboardShortcut, Modifiers, Ui};
+use std::path::PathBuf;
+
+mod boxes;
+mod clipboard;
+mod completion;
+mod cursor;
+mod export;
+mod file;
+mod fold;
+mod holes;
+mod inspector;
+mod outline;
+mod parse;
+mod persistence;
+mod text;
+mod tidy;
+mod violations;
+
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+const DUPLICATE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::D);
+
+/// How the tree is drawn in the central panel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+enum View {
+    /// Nested boxes, with the kids of a node in a row inside of it.
+    #[default]
+    Boxes,
+    /// A top-down node-link diagram with a tidy layout.
+    Tidy,
+    /// An indented list with a row per node.
+    Outline,
+    /// The text that the grammar prints the tree to.
+    Text,
+}
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct App {
-    root: Tree,
-    focus: Index,
+    /// The tree and the focus in it.
+    #[serde(flatten)]
+    cursor: Cursor,
+
+    /// The library of paths that the focus can be wrapped with.
+    templates: Vec<Template>,
+    /// The template that Enter wraps the focus with.
+    selected_template: usize,
+    show_templates: bool,
+    show_inspector: bool,
+    /// Whether the tree is also shown as text, printed with the grammar.
+    show_text: bool,
+    view: View,
+    /// The schema that the tree is checked against, if any.
+    schema: Option<Schema>,
+
+    /// The file the tree was last opened from or saved to.
+    file_path: Option<PathBuf>,
+    /// Whether the tree has changed since it was last opened or saved.
+    dirty: bool,
+    /// The depth below which exported diagrams leave out nodes, if any.
+    export_max_depth: Option<usize>,
+    /// The depth below which "Fold below depth" hides nodes.
+    fold_depth: usize,
+
+    #[serde(skip)]
+    history: History,
+
+    /// Where each node of the tree is, by its id.
+    #[serde(skip)]
+    node_ids: NodeIds,
+
+    /// The sizes of the boxes in the boxes view.
+    #[serde(skip)]
+    box_sizes: boxes::SizeCache,
+
+    /// The rows of the outline view.
+    #[serde(skip)]
+    outline_rows: outline::RowCache,
+
+    /// The layout of the tidy view.
+    #[serde(skip)]
+    tidy_layout: tidy::LayoutCache,
+
+    /// The tree printed with the grammar, for the text panel and view.
+    #[serde(skip)]
+    text_layout: text::LayoutCache,
+
+    /// Where the tree breaks the schema.
+    #[serde(skip)]
+    violations: violations::Violations,
+
+    /// The label being typed in place of the label of the focus, if any.
+    #[serde(skip)]
+    label_draft: Option<String>,
+
+    /// The attribute values and the new attribute being typed in the
+    /// inspector.
+    #[serde(skip)]
+    attr_drafts: inspector::Drafts,
+
+    /// The text in the window for pasting an S-expression, if it is open.
+    #[serde(skip)]
+    sexpr_draft: Option<String>,
+
+    /// The text in the window for parsing an expression, if it is open.
+    #[serde(skip)]
+    expr_draft: Option<String>,
+
+    /// The completion popup, if it is open.
+    #[serde(skip)]
+    completion: Option<completion::Popup>,
+
+    /// Saved state that couldn't be loaded, kept so that it can be backed up
+    /// instead of overwritten.
+    #[serde(skip)]
+    discarded_state: Option<String>,
+
+    /// An action waiting for the user to decide what to do with unsaved
+    /// changes.
+    #[serde(skip)]
+    pending: Option<file::PendingAction>,
+    /// Whether closing the window was confirmed despite unsaved changes.
+    #[serde(skip)]
+    allow_close: bool,
+    /// The title last shown in the title bar.
+    #[serde(skip)]
+    title: String,
+    /// An error to show to the user.
+    #[serde(skip)]
+    error_message: Option<String>,
 }
 
 impl Default for App {
     fn default() -> Self {
+        let mut root = tree::big_tree(5, 5);
+        let node_ids = NodeIds::new(&mut root);
         Self {
-            root: tree::big_tree(5, 5),
-            focus: Index::default(),
+            cursor: Cursor::new(root, Index::default()),
+            templates: template::default_templates(),
+            selected_template: 0,
+            show_templates: false,
+            show_inspector: false,
+            show_text: false,
+            view: View::default(),
+            schema: None,
+            file_path: None,
+            dirty: false,
+            export_max_depth: None,
+            fold_depth: 2,
+            history: History::default(),
+            node_ids,
+            box_sizes: boxes::SizeCache::default(),
+            outline_rows: outline::RowCache::default(),
+            tidy_layout: tidy::LayoutCache::default(),
+            text_layout: text::LayoutCache::default(),
+            violations: violations::Violations::default(),
+            label_draft: None,
+            attr_drafts: inspector::Drafts::default(),
+            sexpr_draft: None,
+            expr_draft: None,
+            completion: None,
+            discarded_state: None,
+            pending: None,
+            allow_close: false,
+            title: String::new(),
+            error_message: None,
         }
     }
 }
@@ -27,135 +184,333 @@ impl App {
 
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
-        if false {
-            if let Some(storage) = cc.storage {
-                eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default()
-            } else {
-                Default::default()
-            }
+        if let Some(storage) = cc.storage {
+            persistence::load(storage)
         } else {
             Default::default()
         }
     }
 
-    fn render_tree(&mut self, ui: &mut Ui, ctx: &egui::Context) {
-        type IndexToResponse = HashMap<Index, egui::Response>;
-        let mut index_to_response: IndexToResponse = HashMap::new();
-        let mut moved = false;
-
-        fn go(
-            app: &mut App,
-            ui: &mut Ui,
-            index_to_response: &mut IndexToResponse,
-            moved: &mut bool,
-            outside_focus: bool,
-            tree: &Tree,
-            index: Index,
-        ) {
-            ui.vertical(|ui| {
-                let frame = Frame::new()
-                    .inner_margin(12)
-                    .outer_margin(12)
-                    .corner_radius(12)
-                    .shadow(egui::Shadow {
-                        offset: [4, 4],
-                        blur: 12,
-                        spread: 0,
-                        color: egui::Color32::from_black_alpha(180),
-                    })
-                    .fill(egui::Color32::BLUE)
-                    .stroke(if outside_focus && index.len() == app.focus.len() {
-                        egui::Stroke::new(2.0, egui::Color32::RED)
-                    } else {
-                        egui::Stroke::new(2.0, egui::Color32::BLACK)
-                    });
-
-                let frame_response = frame.show(ui, |ui| {
-                    let label = ui.button(egui::RichText::new(tree.label.clone()));
-                    if label.clicked() {
-                        app.focus = index.clone();
-                        *moved = true;
-                    }
-
-                    ui.horizontal(|ui| {
-                        for (i, kid) in tree.kids.iter().enumerate() {
-                            let mut index_kid = index.clone();
-                            index_kid.push(i);
-
-                            let outside_focus_kid = outside_focus
-                                && match app.focus.get(index_kid.len() - 1) {
-                                    None => false,
-                                    Some(j) => i == j,
-                                };
-
-                            go(
-                                app,
-                                ui,
-                                index_to_response,
-                                moved,
-                                outside_focus_kid,
-                                kid,
-                                index_kid,
-                            );
-                        }
-                    })
-                });
+    /// Renders the text field for editing the label of the focus. Enter
+    /// commits the new label and Escape cancels.
+    fn render_label_draft(&mut self, ui: &mut Ui) {
+        let Some(draft) = &mut self.label_draft else {
+            return;
+        };
+        let response = ui.add(egui::TextEdit::singleline(draft).desired_width(80.0));
+        if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) {
+            self.label_draft = None;
+        } else if response.lost_focus()
+            && ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Enter))
+        {
+            self.commit_label_draft();
+        } else {
+            response.request_focus();
+        }
+    }
 
-                index_to_response.insert(index.clone(), frame_response.response);
+    fn start_label_draft(&mut self) {
+        if let Ok(tree) = self.cursor.focused() {
+            self.label_draft = Some(tree.label.clone());
+        }
+    }
 
-                ui.set_max_size(ui.min_size());
+    fn commit_label_draft(&mut self) {
+        let Some(label) = self.label_draft.take() else {
+            return;
+        };
+        if self.cursor.focused().is_ok_and(|tree| tree.label != label) {
+            self.apply_edit(Edit::ReplaceLabel {
+                index: self.cursor.focus().clone(),
+                label,
             });
         }
+    }
 
-        go(
-            self,
-            ui,
-            &mut index_to_response,
-            &mut moved,
-            true,
-            &self.root.clone(),
-            Index::default(),
-        );
+    /// Handles the keyboard shortcuts for moving the focus and editing the
+    /// tree. Returns whether the focus moved.
+    fn handle_keys(&mut self, ctx: &egui::Context) -> bool {
+        // the keys belong to the label being edited, to the completion popup,
+        // or to another text field
+        if self.label_draft.is_some() || self.completion.is_some() || ctx.wants_keyboard_input() {
+            return false;
+        }
 
-        if ctx.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
-            self.focus.move_up_unsafe();
-            moved = true;
+        let mut moved = self.handle_clipboard_events(ctx);
+        // check redo first, since the undo shortcut also matches with shift held
+        if ctx.input_mut(|i| i.consume_shortcut(&REDO_SHORTCUT)) {
+            moved = self.redo();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&UNDO_SHORTCUT)) {
+            moved = self.undo();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&DUPLICATE_SHORTCUT)) {
+            moved = self.apply_edit(Edit::Duplicate {
+                index: self.cursor.focus().clone(),
+            });
+        } else if ctx.input_mut(|i| i.consume_shortcut(&fold::FOLD_SHORTCUT)) {
+            self.set_folded(&self.cursor.focus().clone(), true);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&fold::UNFOLD_SHORTCUT)) {
+            self.set_folded(&self.cursor.focus().clone(), false);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&holes::PREV_HOLE_SHORTCUT)) {
+            // check this first, since the next hole shortcut also matches with
+            // shift held
+            let (root, focus) = self.cursor.root_and_focus_mut();
+            let result = focus.move_prev_hole(root);
+            moved = self.check_move(result);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&holes::NEXT_HOLE_SHORTCUT)) {
+            let (root, focus) = self.cursor.root_and_focus_mut();
+            let result = focus.move_next_hole(root);
+            moved = self.check_move(result);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&completion::COMPLETE_SHORTCUT)) {
+            self.open_completion();
+        } else if ctx.input(|i| i.key_pressed(egui::Key::F2)) {
+            self.start_label_draft();
+        } else if ctx.input(|i| i.key_pressed(egui::Key::Delete)) {
+            moved = self.apply_edit(Edit::Delete {
+                index: self.cursor.focus().clone(),
+            });
+        } else if ctx.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
+            let result = self.cursor.move_up();
+            moved = self.check_move(result);
         } else if ctx.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
-            self.focus.move_down_unsafe(0);
-            moved = true;
+            let depth = self.cursor.focus().len();
+            moved = match self.cursor.move_down(0) {
+                // the focus is a leaf
+                Err(tree::Error::OutOfBounds { depth: at, .. }) if at == depth => false,
+                result => self.check_move(result),
+            };
         } else if ctx.input(|i| i.key_pressed(egui::Key::ArrowLeft)) {
-            let result = self.focus.move_prev(&self.root);
-            if let Err(msg) = &result {
-                println!("move prev error: {msg}")
-            }
-            moved = result.is_ok();
+            let result = self.cursor.move_prev();
+            moved = self.check_move(result);
         } else if ctx.input(|i| i.key_pressed(egui::Key::ArrowRight)) {
-            let result = self.focus.move_next(&self.root);
-            if let Err(msg) = &result {
-                println!("move next error: {msg}")
-            }
-            moved = result.is_ok();
+            let result = self.cursor.move_next();
+            moved = self.check_move(result);
         } else if ctx.input(|i| i.key_pressed(egui::Key::Enter)) {
-            self.root.wrap_with_path_at_index(
-                &self.focus,
-                vec![tree::Tooth {
-                    label: "A".to_string(),
-                    kids_left: vec![tree::Tree {
-                        label: "B".to_string(),
-                        kids: vec![],
-                    }],
-                    kids_right: vec![tree::Tree {
-                        label: "C".to_string(),
-                        kids: vec![],
-                    }],
-                }],
-            );
-            moved = true;
+            moved = self.wrap_with_template(self.selected_template);
         }
-
         if moved {
-            let response = index_to_response.get(&self.focus).unwrap();
-            response.scroll_to_me(Some(egui::Align::LEFT));
+            self.reveal_focus();
+        }
+        moved
+    }
+
+    /// Wraps the focus with the path of the template at `i`. Returns whether
+    /// it succeeded.
+    fn wrap_with_template(&mut self, i: usize) -> bool {
+        let Some(template) = self.templates.get(i) else {
+            return false;
+        };
+        self.apply_edit(Edit::WrapPath {
+            index: self.cursor.focus().clone(),
+            path: template.path.clone(),
+        })
+    }
+
+    fn templates_panel(&mut self, ui: &mut Ui) {
+        ui.heading("Templates");
+        ui.label("Enter wraps the focus with the selected template.");
+
+        for (i, template) in self.templates.iter().enumerate() {
+            ui.radio_value(&mut self.selected_template, i, &template.name);
+        }
+        if ui.button("New template").clicked() {
+            self.templates
+                .push(Template::mk("new", vec![Tooth::mk("new", &[], &[])]));
+            self.selected_template = self.templates.len() - 1;
+        }
+
+        ui.separator();
+
+        if let Some(template) = self.templates.get_mut(self.selected_template) {
+            if render_template_editor(ui, template) {
+                self.templates.remove(self.selected_template);
+                self.selected_template = self.selected_template.saturating_sub(1);
+            }
+        }
+    }
+
+    fn edit_menu(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        let undo = egui::Button::new("Undo").shortcut_text(ctx.format_shortcut(&UNDO_SHORTCUT));
+        if ui.add_enabled(self.history.can_undo(), undo).clicked() {
+            self.undo();
+        }
+        let redo = egui::Button::new("Redo").shortcut_text(ctx.format_shortcut(&REDO_SHORTCUT));
+        if ui.add_enabled(self.history.can_redo(), redo).clicked() {
+            self.redo();
+        }
+
+        ui.separator();
+
+        ui.menu_button("Wrap with", |ui| {
+            for i in 0..self.templates.len() {
+                let mut button = egui::Button::new(&self.templates[i].name);
+                if i == self.selected_template {
+                    button = button.shortcut_text("Enter");
+                }
+                if ui.add(button).clicked() {
+                    self.wrap_with_template(i);
+                }
+            }
+        });
+        let rename = egui::Button::new("Rename").shortcut_text("F2");
+        if ui.add_enabled(self.label_draft.is_none(), rename).clicked() {
+            self.start_label_draft();
+        }
+        let is_root = self.cursor.focus().is_empty();
+        let delete = egui::Button::new("Delete").shortcut_text("Del");
+        if ui.add_enabled(!is_root, delete).clicked() {
+            self.apply_edit(Edit::Delete {
+                index: self.cursor.focus().clone(),
+            });
+        }
+        let duplicate =
+            egui::Button::new("Duplicate").shortcut_text(ctx.format_shortcut(&DUPLICATE_SHORTCUT));
+        if ui.add_enabled(!is_root, duplicate).clicked() {
+            self.apply_edit(Edit::Duplicate {
+                index: self.cursor.focus().clone(),
+            });
+        }
+
+        ui.separator();
+
+        self.hole_menu_items(ui, ctx);
+        let complete = egui::Button::new("Complete…")
+            .shortcut_text(ctx.format_shortcut(&completion::COMPLETE_SHORTCUT));
+        if ui.add_enabled(self.schema.is_some(), complete).clicked() {
+            self.open_completion();
+        }
+        if ui.button("Parse expression…").clicked() {
+            self.expr_draft = Some(String::new());
+        }
+
+        ui.separator();
+
+        if ui.button("Copy as S-expression").clicked() {
+            self.copy_focus(ctx);
+        }
+        if ui.button("Paste S-expression…").clicked() {
+            self.sexpr_draft = Some(String::new());
+        }
+    }
+
+    /// Applies `edit` to the tree and records it in the history. Returns
+    /// whether it succeeded.
+    fn apply_edit(&mut self, edit: Edit) -> bool {
+        let scope = edit.scope();
+        let result = self.edit_in_scope(&scope, |app| {
+            let (root, focus) = app.cursor.root_and_focus_mut();
+            app.history.apply(edit, root, focus)
+        });
+        match result {
+            Ok(()) => {
+                self.dirty = true;
+                true
+            }
+            Err(err) => {
+                log::warn!("failed to apply edit: {err}");
+                false
+            }
+        }
+    }
+
+    fn undo(&mut self) -> bool {
+        let Some(scope) = self.history.next_undo().map(Edit::scope) else {
+            return false;
+        };
+        let result = self.edit_in_scope(&scope, |app| {
+            let (root, focus) = app.cursor.root_and_focus_mut();
+            app.history.undo(root, focus)
+        });
+        match result {
+            Ok(undone) => {
+                self.dirty |= undone;
+                undone
+            }
+            Err(err) => {
+                log::warn!("failed to undo: {err}");
+                false
+            }
+        }
+    }
+
+    fn redo(&mut self) -> bool {
+        let Some(scope) = self.history.next_redo().map(Edit::scope) else {
+            return false;
+        };
+        let result = self.edit_in_scope(&scope, |app| {
+            let (root, focus) = app.cursor.root_and_focus_mut();
+            app.history.redo(root, focus)
+        });
+        match result {
+            Ok(redone) => {
+                self.dirty |= redone;
+                redone
+            }
+            Err(err) => {
+                log::warn!("failed to redo: {err}");
+                false
+            }
+        }
+    }
+
+    /// Runs `edit`, which changes at most the subtree at `scope`, and brings
+    /// what is kept about the nodes of that subtree up to date.
+    fn edit_in_scope<T>(&mut self, scope: &Index, edit: impl FnOnce(&mut Self) -> T) -> T {
+        self.node_ids.forget(self.cursor.root(), scope);
+        let result = edit(self);
+        self.node_ids.record(self.cursor.root_mut(), scope);
+        self.invalidate_layouts(scope);
+        self.violations.clear();
+        result
+    }
+
+    /// Forgets what the views laid out for the subtree at `scope`, after it
+    /// changed.
+    fn invalidate_layouts(&mut self, scope: &Index) {
+        self.box_sizes.invalidate(scope);
+        self.outline_rows.invalidate(self.cursor.root(), scope);
+        self.tidy_layout.invalidate(scope);
+        self.text_layout.invalidate(scope);
+    }
+
+    fn show_error(&mut self, message: String) {
+        log::error!("{message}");
+        self.error_message = Some(message);
+    }
+
+    fn error_window(&mut self, ctx: &egui::Context) {
+        let Some(message) = &self.error_message else {
+            return;
+        };
+        let mut dismissed = false;
+        egui::Window::new("Error")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(message);
+                dismissed = ui.button("OK").clicked();
+            });
+        if dismissed {
+            self.error_message = None;
+        }
+    }
+
+    /// Returns whether a move of the focus succeeded. Running into the edge of
+    /// the tree is expected; any other failure means the focus is out of sync
+    /// with the tree, so it is reset to the root.
+    fn check_move(&mut self, result: Result<(), tree::Error>) -> bool {
+        match result {
+            Ok(()) => true,
+            Err(
+                tree::Error::AtRoot
+                | tree::Error::NoSibling
+                | tree::Error::AtEnd
+                | tree::Error::NoHole,
+            ) => false,
+            Err(err) => {
+                log::warn!("resetting invalid focus {:?}: {err}", self.cursor.focus());
+                self.cursor.set_focus(Index::default());
+                true
+            }
         }
     }
 }
@@ -163,40 +518,169 @@ impl App {
 impl eframe::App for App {
     /// Called by the framework to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        persistence::save(self, storage);
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // NOTE: no files or File->Quit on web pages!
+        let is_web = cfg!(target_arch = "wasm32");
+        if !is_web {
+            self.handle_close_request(ctx);
+            self.handle_file_shortcuts(ctx);
+            self.update_title(ctx);
+        }
+
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::MenuBar::new().ui(ui, |ui| {
-                // NOTE: no File->Quit on web pages!
-                let is_web = cfg!(target_arch = "wasm32");
                 if !is_web {
-                    ui.menu_button("File", |ui| {
-                        if ui.button("Quit").clicked() {
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                        }
-                    });
+                    ui.menu_button("File", |ui| self.file_menu(ui, ctx));
                     ui.add_space(16.0);
                 }
 
+                ui.menu_button("Edit", |ui| self.edit_menu(ui, ctx));
+                ui.menu_button("Fold", |ui| self.fold_menu(ui, ctx));
+                ui.menu_button("Schema", |ui| self.schema_menu(ui));
+                ui.toggle_value(&mut self.show_templates, "Templates");
+                ui.toggle_value(&mut self.show_inspector, "Inspector");
+                ui.toggle_value(&mut self.show_text, "Text panel");
+                ui.add_space(16.0);
+
+                ui.selectable_value(&mut self.view, View::Boxes, "Boxes");
+                ui.selectable_value(&mut self.view, View::Tidy, "Tidy tree");
+                ui.selectable_value(&mut self.view, View::Outline, "Outline");
+                ui.selectable_value(&mut self.view, View::Text, "Text");
+                ui.add_space(16.0);
+
                 egui::widgets::global_theme_preference_buttons(ui);
             });
         });
 
+        self.unsaved_changes_dialog(ctx);
+        self.paste_window(ctx);
+        self.parse_window(ctx);
+        self.completion_window(ctx);
+        self.error_window(ctx);
+        self.update_violations();
+
+        if self.schema.is_some() {
+            egui::TopBottomPanel::bottom("violations_panel")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| self.violations_panel(ui));
+                });
+        }
+        if self.show_text {
+            egui::TopBottomPanel::bottom("text_panel")
+                .resizable(true)
+                .show(ctx, |ui| {
+                    egui::ScrollArea::both().show(ui, |ui| self.text_panel(ui));
+                });
+        }
+        if self.show_templates {
+            egui::SidePanel::right("templates_panel").show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| self.templates_panel(ui));
+            });
+        }
+        if self.show_inspector {
+            egui::SidePanel::right("inspector_panel").show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| self.inspector_panel(ui));
+            });
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("tree-editor-with-egui");
 
-            ui.label(format!("focus: {:?}", self.focus));
+            let focus_id = self.cursor.focused().ok().and_then(|tree| tree.id);
+            let focus = self.cursor.focus();
+            match focus_id {
+                Some(id) => ui.label(format!("focus: {focus:?} {id}")),
+                None => ui.label(format!("focus: {focus:?}")),
+            };
 
+            // the outline scrolls by itself, so that it lays out only the rows
+            // in view
+            if self.view == View::Outline {
+                ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
+                self.render_outline(ui, ctx);
+                return;
+            }
             egui::ScrollArea::both()
                 .auto_shrink([false, true])
                 .scroll_source(egui::containers::scroll_area::ScrollSource::MOUSE_WHEEL)
                 .show(ui, |ui| {
                     ui.style_mut().wrap_mode = Some(egui::TextWrapMode::Extend);
-                    self.render_tree(ui, ctx);
-                })
+                    match self.view {
+                        View::Boxes => self.render_boxes(ui, ctx),
+                        View::Tidy => self.render_tidy_tree(ui, ctx),
+                        View::Outline => {}
+                        View::Text => self.render_text_view(ui, ctx),
+                    }
+                });
         });
     }
 }
+
+/// Renders the fields for editing `template`. Returns whether the template
+/// should be deleted.
+fn render_template_editor(ui: &mut Ui, template: &mut Template) -> bool {
+    ui.horizontal(|ui| {
+        ui.label("name");
+        ui.text_edit_singleline(&mut template.name);
+    });
+
+    let mut removed_tooth = None;
+    for (i, tooth) in template.path.iter_mut().enumerate() {
+        ui.push_id(i, |ui| {
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label("label");
+                    ui.add(egui::TextEdit::singleline(&mut tooth.label).desired_width(80.0));
+                    if ui.small_button("✖").on_hover_text("Remove tooth").clicked() {
+                        removed_tooth = Some(i);
+                    }
+                });
+                ui.push_id("left", |ui| {
+                    render_kids_editor(ui, "left", &mut tooth.kids_left);
+                });
+                ui.push_id("right", |ui| {
+                    render_kids_editor(ui, "right", &mut tooth.kids_right);
+                });
+            });
+        });
+    }
+    if let Some(i) = removed_tooth {
+        template.path.remove(i);
+    }
+    if ui.button("Add tooth").clicked() {
+        template.path.push(Tooth::mk("new", &[], &[]));
+    }
+
+    ui.separator();
+    ui.button("Delete template").clicked()
+}
+
+/// Renders the fields for editing the labels of `kids`. New kids start out as
+/// holes.
+fn render_kids_editor(ui: &mut Ui, name: &str, kids: &mut Vec<Tree>) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label(name);
+        let mut removed = None;
+        for (i, kid) in kids.iter_mut().enumerate() {
+            ui.add(
+                egui::TextEdit::singleline(&mut kid.label)
+                    .id_salt(i)
+                    .desired_width(40.0),
+            );
+            if ui.small_button("✖").on_hover_text("Remove kid").clicked() {
+                removed = Some(i);
+            }
+        }
+        if let Some(i) = removed {
+            kids.remove(i);
+        }
+        if ui.small_button("+").on_hover_text("Add kid").clicked() {
+            kids.push(Tree::hole());
+        }
+    });
+}
@@ -0,0 +1,374 @@
+//! The boxes view: the tree as nested frames, with the kids of a node in a row
+//! inside of it.
+//!
+//! Only the boxes that intersect the visible part of the scroll area are laid
+//! out and drawn, so the view stays fast for very large trees. That needs the
+//! sizes of all of the boxes up front. They are measured once and kept in a
+//! [`SizeCache`] that mirrors the tree, and an edit only forgets the sizes of
+//! the subtree that it changed and of the ancestors of that subtree.
+
+use super::App;
+use super::violations::Violations;
+use crate::tree::export::svg;
+use crate::tree::{self, Index, Tree};
+use egui::{Frame, Pos2, Rect, Ui, Vec2};
+use std::fmt::Display;
+
+/// The measured size of the box of a node, and of the boxes inside of it.
+#[derive(Debug, Default)]
+struct Measured {
+    /// The size of the box, including its outer margin, or `None` if it has
+    /// to be measured again.
+    size: Option<Vec2>,
+    label_width: f32,
+    /// The text and width of the placeholder that stands in for the kids of a
+    /// folded node.
+    placeholder: Option<(String, f32)>,
+    kids: Vec<Measured>,
+}
+
+impl Measured {
+    fn measure<L: Display>(
+        &mut self,
+        tree: &Tree<L>,
+        text_width: &impl Fn(String) -> f32,
+        style: &svg::Style,
+    ) -> Vec2 {
+        if let Some(size) = self.size {
+            return size;
+        }
+
+        self.label_width = text_width(tree.label.to_string()) + 2.0 * style.label_padding;
+        let row: Vec<[f32; 2]> = if tree.is_folded() {
+            self.kids.clear();
+            let text = svg::fold_placeholder(tree);
+            let width = text_width(text.clone()) + 2.0 * style.label_padding;
+            self.placeholder = Some((text, width));
+            vec![[width, style.label_height]]
+        } else {
+            self.placeholder = None;
+            if self.kids.len() != tree.kids.len() {
+                self.kids.clear();
+                self.kids.resize_with(tree.kids.len(), Self::default);
+            }
+            self.kids
+                .iter_mut()
+                .zip(&tree.kids)
+                .map(|(measured, kid)| measured.measure(kid, text_width, style).into())
+                .collect()
+        };
+        let size = Vec2::from(style.box_size(self.label_width, &row));
+        self.size = Some(size);
+        size
+    }
+}
+
+/// The sizes of the boxes of the tree, kept between frames.
+#[derive(Debug, Default)]
+pub struct SizeCache {
+    root: Measured,
+    /// The font that the labels were measured with.
+    font_id: Option<egui::FontId>,
+}
+
+impl SizeCache {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Forgets the sizes of the subtree at `index` and of its ancestors.
+    pub fn invalidate(&mut self, index: &Index) {
+        let mut measured = &mut self.root;
+        for step in index {
+            measured.size = None;
+            let Some(kid) = measured.kids.get_mut(*step) else {
+                return;
+            };
+            measured = kid;
+        }
+        *measured = Measured::default();
+    }
+
+    /// The rect of the box of the node at `index`, where the box of the root
+    /// starts at `origin`, if it has been measured.
+    fn rect_at(&self, index: &Index, origin: Pos2, style: &svg::Style) -> Option<Rect> {
+        let mut measured = &self.root;
+        let mut min = origin;
+        for step in index {
+            min += Vec2::splat(style.margin());
+            min.y += style.label_height + style.label_spacing;
+            for kid in measured.kids.get(..*step)? {
+                min.x += kid.size?.x + style.kid_spacing;
+            }
+            measured = measured.kids.get(*step)?;
+        }
+        Some(Rect::from_min_size(min, measured.size?))
+    }
+}
+
+/// The frame of a box, in the same style as SVG exports, so that they look
+/// like the view, with an outline of `stroke_color`. Holes are gray, so that
+/// they stand out as yet to be filled in.
+fn frame(style: &svg::Style, is_hole: bool, stroke_color: egui::Color32) -> Frame {
+    Frame::new()
+        .inner_margin(style.inner_margin)
+        .outer_margin(style.outer_margin)
+        .corner_radius(style.corner_radius)
+        .shadow(egui::Shadow {
+            offset: [4, 4],
+            blur: 12,
+            spread: 0,
+            color: egui::Color32::from_black_alpha(180),
+        })
+        .fill(if is_hole {
+            egui::Color32::DARK_GRAY
+        } else {
+            egui::Color32::BLUE
+        })
+        .stroke(egui::Stroke::new(style.stroke_width, stroke_color))
+}
+
+/// Draws the visible boxes, and collects what the user did to them, to act on
+/// once they are drawn.
+struct Painter<'a> {
+    style: svg::Style,
+    focus: &'a Index,
+    /// The nodes that are outlined as breaking the schema.
+    violations: &'a Violations,
+    warn_color: egui::Color32,
+    is_drafting: bool,
+    clicked: Option<Index>,
+    double_clicked: bool,
+    unfolded: Option<Index>,
+    /// Where the label of the focus is, if it is being edited.
+    label_draft_rect: Option<Rect>,
+}
+
+impl Painter<'_> {
+    fn draw<L: Display>(
+        &mut self,
+        ui: &mut Ui,
+        tree: &Tree<L>,
+        measured: &Measured,
+        min: Pos2,
+        index: &mut Index,
+    ) {
+        let style = self.style;
+        let rect = Rect::from_min_size(min, measured.size.unwrap_or_default());
+        let is_focus = *index == *self.focus;
+        let content = rect.shrink(style.margin());
+        let stroke_color = if is_focus {
+            egui::Color32::RED
+        } else if self.violations.is_invalid(index) {
+            self.warn_color
+        } else {
+            egui::Color32::BLACK
+        };
+        let label = tree.label.to_string();
+        let is_hole = tree.kids.is_empty() && tree::is_hole_label(&label);
+        let frame = frame(&style, is_hole, stroke_color);
+        ui.painter().add(frame.paint(content));
+
+        let label_rect = Rect::from_min_size(
+            content.min,
+            Vec2::new(measured.label_width, style.label_height),
+        );
+        if is_focus && self.is_drafting {
+            self.label_draft_rect = Some(label_rect);
+        } else {
+            let label = button(ui, label_rect, &*index, &label);
+            if label.clicked() {
+                self.clicked = Some(index.clone());
+            }
+            if label.double_clicked() {
+                self.double_clicked = true;
+            }
+        }
+
+        let mut kid_min = content.min + Vec2::new(0.0, style.label_height + style.label_spacing);
+        if let Some((text, width)) = &measured.placeholder {
+            let placeholder_rect =
+                Rect::from_min_size(kid_min, Vec2::new(*width, style.label_height));
+            let placeholder = button(ui, placeholder_rect, ("placeholder", &*index), text);
+            if placeholder.on_hover_text("Unfold").clicked() {
+                self.unfolded = Some(index.clone());
+            }
+            return;
+        }
+
+        let visible = ui.clip_rect();
+        for (i, (kid, kid_measured)) in tree.kids.iter().zip(&measured.kids).enumerate() {
+            if kid_min.x > visible.max.x {
+                break;
+            }
+            let kid_size = kid_measured.size.unwrap_or_default();
+            if visible.intersects(Rect::from_min_size(kid_min, kid_size)) {
+                index.push(i);
+                self.draw(ui, kid, kid_measured, kid_min, index);
+                index.pop();
+            }
+            kid_min.x += kid_size.x + style.kid_spacing;
+        }
+    }
+}
+
+/// A button in `rect`, with an id that doesn't depend on which of the other
+/// boxes happen to be visible.
+fn button(ui: &mut Ui, rect: Rect, id_salt: impl std::hash::Hash, text: &str) -> egui::Response {
+    let mut ui = ui.new_child(egui::UiBuilder::new().id_salt(id_salt).max_rect(rect));
+    ui.add(egui::Button::new(text))
+}
+
+impl App {
+    pub(super) fn render_boxes(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        // handle the keys first, so that the boxes are drawn for the tree as
+        // it is after the edits that they make
+        let mut moved = self.handle_keys(ctx);
+
+        let style = svg::Style::DEFAULT;
+        let font_id = egui::TextStyle::Button.resolve(ui.style());
+        if self.box_sizes.font_id.as_ref() != Some(&font_id) {
+            self.box_sizes.clear();
+            self.box_sizes.font_id = Some(font_id.clone());
+        }
+        let size = ui.fonts(|fonts| {
+            let text_width = |text: String| {
+                fonts
+                    .layout_no_wrap(text, font_id.clone(), egui::Color32::WHITE)
+                    .size()
+                    .x
+            };
+            self.box_sizes
+                .root
+                .measure(self.cursor.root(), &text_width, &style)
+        });
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+
+        let (root, focus) = self.cursor.root_and_focus();
+        let mut painter = Painter {
+            style,
+            focus,
+            violations: &self.violations,
+            warn_color: ui.visuals().warn_fg_color,
+            is_drafting: self.label_draft.is_some(),
+            clicked: None,
+            double_clicked: false,
+            unfolded: None,
+            label_draft_rect: None,
+        };
+        if ui.is_rect_visible(rect) {
+            painter.draw(
+                ui,
+                root,
+                &self.box_sizes.root,
+                rect.min,
+                &mut Index::default(),
+            );
+        }
+        let Painter {
+            clicked,
+            double_clicked,
+            unfolded,
+            label_draft_rect,
+            ..
+        } = painter;
+
+        if let Some(index) = clicked {
+            self.cursor.set_focus(index);
+            self.label_draft = None;
+            moved = true;
+        }
+        if double_clicked {
+            self.start_label_draft();
+        }
+        if let Some(index) = unfolded {
+            self.set_folded(&index, false);
+        }
+        if let Some(rect) = label_draft_rect {
+            let mut ui = ui.new_child(egui::UiBuilder::new().max_rect(rect));
+            self.render_label_draft(&mut ui);
+        }
+
+        if moved {
+            if let Some(rect) = self
+                .box_sizes
+                .rect_at(self.cursor.focus(), rect.min, &style)
+            {
+                ui.scroll_to_rect(rect, Some(egui::Align::LEFT));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{export, sexpr};
+
+    fn text_width() -> impl Fn(String) -> f32 {
+        |text| 10.0 * text.chars().count() as f32
+    }
+
+    fn measure(cache: &mut SizeCache, tree: &Tree) -> Vec2 {
+        cache
+            .root
+            .measure(tree, &text_width(), &svg::Style::DEFAULT)
+    }
+
+    /// Asserts that the boxes of the view and of an SVG export are the same.
+    fn assert_same_layout(measured: &Measured, boxed: &svg::Boxed) {
+        assert_eq!(measured.size.map(<[f32; 2]>::from), Some(boxed.size));
+        assert_eq!(measured.placeholder, boxed.placeholder);
+        assert_eq!(measured.kids.len(), boxed.kids.len());
+        for (measured, boxed) in measured.kids.iter().zip(&boxed.kids) {
+            assert_same_layout(measured, boxed);
+        }
+    }
+
+    #[test]
+    fn boxes_size_cache_test1() {
+        let style = svg::Style::DEFAULT;
+        let mut tree = Tree::mk("A", &[Tree::mk("B", &[]), Tree::mk("C", &[])]);
+        let mut cache = SizeCache::default();
+
+        let size = measure(&mut cache, &tree);
+        let c = cache.rect_at(&Index(vec![1]), Pos2::ZERO, &style).unwrap();
+        assert_eq!(c.max.x + style.margin(), size.x);
+
+        // a stale cache keeps the old sizes, until the edited label is
+        // invalidated
+        tree.kids[0].label = "BBB".to_owned();
+        assert_eq!(measure(&mut cache, &tree), size);
+        cache.invalidate(&Index(vec![0]));
+        assert!(cache.root.kids[1].size.is_some());
+        assert_eq!(measure(&mut cache, &tree).x, size.x + 20.0);
+
+        tree.kids[1].kids.push(Tree::mk("D", &[]));
+        cache.invalidate(&Index(vec![1]));
+        measure(&mut cache, &tree);
+        assert!(
+            cache
+                .rect_at(&Index(vec![1, 0]), Pos2::ZERO, &style)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn boxes_svg_layout_test1() {
+        let mut tree = sexpr::parse("(A (B D E) (C F))").unwrap();
+        tree.kids[0].folded = true;
+        let mut cache = SizeCache::default();
+
+        measure(&mut cache, &tree);
+        let boxed = svg::layout(
+            &tree,
+            &mut Index::default(),
+            &export::Options::default(),
+            &svg::Style::DEFAULT,
+            &text_width(),
+        );
+
+        assert_same_layout(&cache.root, &boxed);
+        assert!(cache.root.kids[0].placeholder.is_some());
+    }
+}
@@ -0,0 +1,86 @@
+//! Copying and pasting subtrees as S-expressions.
+
+use super::App;
+use crate::history::Edit;
+use crate::tree::sexpr;
+
+impl App {
+    pub(super) fn copy_focus(&mut self, ctx: &egui::Context) {
+        if let Ok(tree) = self.cursor.focused() {
+            ctx.copy_text(sexpr::to_string(tree));
+        }
+    }
+
+    /// Replaces the focus with the tree that `text` is an S-expression of.
+    /// Returns whether it was replaced.
+    pub(super) fn paste_at_focus(&mut self, text: &str) -> bool {
+        match sexpr::parse(text) {
+            Ok(tree) => self.apply_edit(Edit::Replace {
+                index: self.cursor.focus().clone(),
+                tree,
+            }),
+            Err(err) => {
+                self.show_error(format!("Failed to paste S-expression: {err}"));
+                false
+            }
+        }
+    }
+
+    /// Handles the copy and paste events that the platform sends for the
+    /// usual shortcuts. Returns whether the focus changed.
+    pub(super) fn handle_clipboard_events(&mut self, ctx: &egui::Context) -> bool {
+        let mut copied = false;
+        let mut pasted = None;
+        ctx.input(|i| {
+            for event in &i.events {
+                match event {
+                    egui::Event::Copy => copied = true,
+                    egui::Event::Paste(text) => pasted = Some(text.clone()),
+                    _ => {}
+                }
+            }
+        });
+        if copied {
+            self.copy_focus(ctx);
+        }
+        pasted.is_some_and(|text| self.paste_at_focus(&text))
+    }
+
+    /// Shows the window for typing in an S-expression to paste, if it is open.
+    pub(super) fn paste_window(&mut self, ctx: &egui::Context) {
+        let Some(draft) = &mut self.sexpr_draft else {
+            return;
+        };
+        let mut open = true;
+        let mut pasted = None;
+        egui::Window::new("Paste S-expression")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("Replaces the focus with the tree:");
+                ui.add(
+                    egui::TextEdit::multiline(draft)
+                        .code_editor()
+                        .desired_rows(8),
+                );
+                let result = sexpr::parse(draft);
+                if let Err(err) = &result {
+                    ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+                }
+                if ui
+                    .add_enabled(result.is_ok(), egui::Button::new("Paste"))
+                    .clicked()
+                {
+                    pasted = result.ok();
+                }
+            });
+        if let Some(tree) = pasted {
+            self.sexpr_draft = None;
+            self.apply_edit(Edit::Replace {
+                index: self.cursor.focus().clone(),
+                tree,
+            });
+        } else if !open {
+            self.sexpr_draft = None;
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! The completion popup: the constructors that the schema allows at the
+//! focus, filtered by what is typed.
+
+use super::App;
+use crate::history::Edit;
+use crate::schema::Completion;
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+pub(super) const COMPLETE_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::Space);
+
+/// The state of the completion popup, while it is open.
+#[derive(Debug, Default)]
+pub struct Popup {
+    /// The text that the labels of the constructors are filtered by.
+    filter: String,
+    /// The position of the highlighted completion among those that match.
+    selected: usize,
+}
+
+impl App {
+    pub(super) fn open_completion(&mut self) {
+        if self.schema.is_some() {
+            self.completion = Some(Popup::default());
+        }
+    }
+
+    /// The completions at the focus whose labels contain `filter`, ignoring
+    /// case.
+    fn matching_completions(&mut self, filter: &str) -> Vec<Completion> {
+        let Some(schema) = &self.schema else {
+            return vec![];
+        };
+        let filter = filter.to_lowercase();
+        let (root, focus) = self.cursor.root_and_focus();
+        schema
+            .completions(root, focus)
+            .into_iter()
+            .filter(|completion| {
+                completion
+                    .constructor()
+                    .label
+                    .to_lowercase()
+                    .contains(&filter)
+            })
+            .collect()
+    }
+
+    /// Puts the constructor of `completion` at the focus, and moves to the
+    /// first hole that it leaves. Returns whether it succeeded.
+    fn complete(&mut self, completion: Completion) -> bool {
+        let index = self.cursor.focus().clone();
+        let edit = match completion {
+            Completion::Fill(constructor) => Edit::Replace {
+                index: index.clone(),
+                tree: constructor.instantiate(),
+            },
+            Completion::Wrap(constructor, slot) => Edit::WrapPath {
+                index: index.clone(),
+                path: vec![constructor.tooth(slot)],
+            },
+        };
+        if !self.apply_edit(edit) {
+            return false;
+        }
+        self.focus_first_hole_in(&index);
+        true
+    }
+
+    /// Shows the completion popup, if it is open. Up and Down move the
+    /// highlight, Enter picks the highlighted completion and Escape closes it.
+    pub(super) fn completion_window(&mut self, ctx: &egui::Context) {
+        let Some(filter) = self.completion.as_ref().map(|popup| popup.filter.clone()) else {
+            return;
+        };
+        let completions = self.matching_completions(&filter);
+        let Some(popup) = &mut self.completion else {
+            return;
+        };
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if up {
+            popup.selected = popup.selected.saturating_sub(1);
+        }
+        if down {
+            popup.selected += 1;
+        }
+        popup.selected = popup.selected.min(completions.len().saturating_sub(1));
+
+        let mut open = true;
+        let mut picked = enter.then_some(popup.selected);
+        egui::Window::new("Complete")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let filter =
+                    ui.add(egui::TextEdit::singleline(&mut popup.filter).hint_text("filter"));
+                filter.request_focus();
+                if filter.changed() {
+                    popup.selected = 0;
+                }
+                if completions.is_empty() {
+                    ui.weak("Nothing fits here.");
+                }
+                for (i, completion) in completions.iter().enumerate() {
+                    let label = ui.selectable_label(i == popup.selected, completion.text());
+                    if label.clicked() {
+                        picked = Some(i);
+                    }
+                    if i == popup.selected && (up || down) {
+                        label.scroll_to_me(None);
+                    }
+                }
+            });
+
+        if let Some(completion) = picked.and_then(|i| completions.into_iter().nth(i)) {
+            self.completion = None;
+            self.complete(completion);
+        } else if !open || escape {
+            self.completion = None;
+        }
+    }
+}
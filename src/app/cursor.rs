@@ -0,0 +1,243 @@
+//! The tree being edited and the focus in it, held as a [`Zipper`].
+//!
+//! Moving the focus and looking at the focused node go through the zipper,
+//! opened at the focus. The views draw from the whole tree and edits are
+//! applied at indices, so for those the zipper is closed up at the root, and
+//! it is opened again at the next move. Either way takes time in the depth of
+//! the focus, and the moves in between take constant time each.
+
+use crate::tree::{Error, Index, Tree, Zipper};
+
+#[derive(Debug, Default)]
+pub struct Cursor {
+    /// Open at the focus, or closed up at the root.
+    zipper: Zipper,
+    /// The index of the focus, kept up to date as the zipper moves.
+    focus: Index,
+    open: bool,
+}
+
+impl Cursor {
+    pub fn new(root: Tree, focus: Index) -> Self {
+        Self {
+            zipper: Zipper::new(root),
+            focus,
+            open: false,
+        }
+    }
+
+    pub fn focus(&self) -> &Index {
+        &self.focus
+    }
+
+    pub fn root(&mut self) -> &Tree {
+        self.close();
+        &self.zipper.focus
+    }
+
+    /// The whole tree, for edits that keep the focus where it is.
+    pub fn root_mut(&mut self) -> &mut Tree {
+        self.close();
+        &mut self.zipper.focus
+    }
+
+    pub fn root_and_focus(&mut self) -> (&Tree, &Index) {
+        self.close();
+        (&self.zipper.focus, &self.focus)
+    }
+
+    /// The whole tree and the focus, for edits that move the focus along.
+    pub fn root_and_focus_mut(&mut self) -> (&mut Tree, &mut Index) {
+        self.close();
+        (&mut self.zipper.focus, &mut self.focus)
+    }
+
+    pub fn set_focus(&mut self, focus: Index) {
+        self.close();
+        self.focus = focus;
+    }
+
+    /// The node at the focus.
+    pub fn focused(&mut self) -> Result<&Tree, Error> {
+        self.open()?;
+        Ok(&self.zipper.focus)
+    }
+
+    pub fn move_up(&mut self) -> Result<(), Error> {
+        self.open()?;
+        self.zipper.move_up()?;
+        self.focus.pop();
+        Ok(())
+    }
+
+    pub fn move_down(&mut self, step: usize) -> Result<(), Error> {
+        self.open()?;
+        self.zipper.move_down(step)?;
+        self.focus.push(step);
+        Ok(())
+    }
+
+    pub fn move_left(&mut self) -> Result<(), Error> {
+        self.open()?;
+        self.zipper.move_left()?;
+        self.focus.move_left_sibling_unsafe();
+        Ok(())
+    }
+
+    pub fn move_right(&mut self) -> Result<(), Error> {
+        self.open()?;
+        self.zipper.move_right()?;
+        self.focus.move_right_sibling_unsafe();
+        Ok(())
+    }
+
+    /// Moves to the previous node in preorder, as [`Index::move_prev`] does.
+    pub fn move_prev(&mut self) -> Result<(), Error> {
+        match self.move_left() {
+            Ok(()) => {
+                while let Some(last) = self.zipper.focus.kids.len().checked_sub(1) {
+                    self.move_down(last)?;
+                }
+                Ok(())
+            }
+            Err(Error::NoSibling) => self.move_up(),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Moves to the next node in preorder, as [`Index::move_next`] does.
+    pub fn move_next(&mut self) -> Result<(), Error> {
+        if self.focused()?.kids.is_empty() {
+            self.move_up_until_right()
+        } else {
+            self.move_down(0)
+        }
+    }
+
+    /// Moves to the nearest sibling on the right of the focus or of one of
+    /// its ancestors, as [`Index::move_up_until_right`] does. If there is
+    /// none, the focus stays where it was.
+    pub fn move_up_until_right(&mut self) -> Result<(), Error> {
+        let mut climbed = vec![];
+        loop {
+            match self.move_right() {
+                Err(Error::NoSibling) => {
+                    climbed.extend(self.focus.0.last());
+                    self.move_up()?;
+                }
+                Err(Error::AtRoot) => {
+                    for step in climbed.into_iter().rev() {
+                        self.move_down(step)?;
+                    }
+                    return Err(Error::AtEnd);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Opens the zipper at the focus, if it isn't already.
+    fn open(&mut self) -> Result<(), Error> {
+        if self.open {
+            return Ok(());
+        }
+        for step in &self.focus {
+            if let Err(err) = self.zipper.move_down(*step) {
+                self.close_zipper();
+                return Err(err);
+            }
+        }
+        self.open = true;
+        Ok(())
+    }
+
+    /// Closes the zipper up at the root, if it isn't already.
+    fn close(&mut self) {
+        if self.open {
+            self.close_zipper();
+            self.open = false;
+        }
+    }
+
+    fn close_zipper(&mut self) {
+        while self.zipper.move_up().is_ok() {}
+    }
+}
+
+/// The cursor is saved as the whole tree and the index of the focus.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Saved<T> {
+    root: T,
+    focus: Index,
+}
+
+impl serde::Serialize for Cursor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let root = if self.open {
+            std::borrow::Cow::Owned(self.zipper.clone().unzip())
+        } else {
+            std::borrow::Cow::Borrowed(&self.zipper.focus)
+        };
+        Saved {
+            root,
+            focus: self.focus.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Cursor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Saved { root, focus } = Saved::<Tree>::deserialize(deserializer)?;
+        Ok(Self::new(root, focus))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::sexpr;
+
+    #[test]
+    fn cursor_move_test1() {
+        let tree = sexpr::parse("(A (B D E) C)").unwrap();
+        let mut cursor = Cursor::new(tree.clone(), Index::default());
+        let mut index = Index::default();
+
+        // the cursor goes through the tree in preorder the way an index does
+        loop {
+            let result = cursor.move_next();
+            assert_eq!(result, index.move_next(&tree));
+            assert_eq!(cursor.focus(), &index);
+            assert_eq!(cursor.focused().unwrap(), tree.at_index(&index).unwrap());
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(cursor.focus(), &Index(vec![1]));
+        loop {
+            let result = cursor.move_prev();
+            assert_eq!(result, index.move_prev(&tree));
+            assert_eq!(cursor.focus(), &index);
+            if result.is_err() {
+                break;
+            }
+        }
+        assert_eq!(cursor.root(), &tree);
+    }
+
+    #[test]
+    fn cursor_open_test1() {
+        let tree = sexpr::parse("(A (B D E) C)").unwrap();
+        let mut cursor = Cursor::new(tree.clone(), Index(vec![0, 1]));
+
+        assert_eq!(cursor.focused().unwrap().label, "E");
+        cursor.root_mut().kids[0].kids[1].label = "F".to_owned();
+        assert_eq!(cursor.focused().unwrap().label, "F");
+
+        cursor.set_focus(Index(vec![3]));
+        assert!(cursor.focused().is_err());
+        assert!(cursor.move_up().is_err());
+        assert_eq!(cursor.root().kids.len(), 2);
+    }
+}
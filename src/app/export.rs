@@ -0,0 +1,97 @@
+//! The Export menu: writing the tree as a diagram for other tools.
+
+use super::App;
+use super::file::pick_save_path;
+use crate::tree::export::{self, Options, svg};
+
+/// A diagram format that the tree can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Dot,
+    Mermaid,
+    Svg,
+}
+
+impl Format {
+    const ALL: [Self; 3] = [Self::Dot, Self::Mermaid, Self::Svg];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Dot => "Graphviz DOT",
+            Self::Mermaid => "Mermaid",
+            Self::Svg => "SVG",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Dot => "dot",
+            Self::Mermaid => "mmd",
+            Self::Svg => "svg",
+        }
+    }
+}
+
+impl App {
+    pub(super) fn export_menu(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        ui.horizontal(|ui| {
+            let mut limit_depth = self.export_max_depth.is_some();
+            ui.checkbox(&mut limit_depth, "Limit depth to");
+            let mut depth = self.export_max_depth.unwrap_or(3);
+            ui.add_enabled(limit_depth, egui::DragValue::new(&mut depth));
+            self.export_max_depth = limit_depth.then_some(depth);
+        });
+
+        ui.separator();
+
+        let is_web = cfg!(target_arch = "wasm32");
+        for format in Format::ALL {
+            if !is_web && ui.button(format!("Save as {}…", format.name())).clicked() {
+                self.save_export(format, ctx);
+            }
+            if ui.button(format!("Copy as {}", format.name())).clicked() {
+                ctx.copy_text(self.export(format, ctx));
+            }
+        }
+    }
+
+    fn export(&mut self, format: Format, ctx: &egui::Context) -> String {
+        let (root, focus) = self.cursor.root_and_focus();
+        let options = Options {
+            focus: Some(focus.clone()),
+            max_depth: self.export_max_depth,
+        };
+        match format {
+            Format::Dot => export::to_dot(root, &options),
+            Format::Mermaid => export::to_mermaid(root, &options),
+            // the labels are measured with the font of the boxes view, so
+            // that the picture is laid out like the view
+            Format::Svg => {
+                let font_id = egui::TextStyle::Button.resolve(&ctx.style());
+                ctx.fonts(|fonts| {
+                    let text_width = |text: String| {
+                        fonts
+                            .layout_no_wrap(text, font_id.clone(), egui::Color32::WHITE)
+                            .size()
+                            .x
+                    };
+                    svg::to_svg_measured(root, &options, &svg::Style::DEFAULT, &text_width)
+                })
+            }
+        }
+    }
+
+    fn save_export(&mut self, format: Format, ctx: &egui::Context) {
+        let current = self
+            .file_path
+            .as_ref()
+            .map(|path| path.with_extension(format.extension()));
+        let Some(path) = pick_save_path(current.as_deref(), format.name(), format.extension())
+        else {
+            return;
+        };
+        if let Err(err) = std::fs::write(&path, self.export(format, ctx)) {
+            self.show_error(format!("Failed to export {}: {err}", path.display()));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! The File menu: reading and writing the tree as a document on disk.
+
+use super::{App, Cursor};
+use crate::document;
+use crate::tree::{Index, NodeIds, Tree};
+use egui::{Key, KeyboardShortcut, Modifiers};
+use std::path::PathBuf;
+
+const NEW_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::N);
+const OPEN_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::O);
+const SAVE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::S);
+const SAVE_AS_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::S);
+
+/// An action that discards the current tree, so it has to wait until the user
+/// decides what to do with unsaved changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingAction {
+    New,
+    Open,
+    Quit,
+}
+
+impl App {
+    pub(super) fn file_menu(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        let new = egui::Button::new("New").shortcut_text(ctx.format_shortcut(&NEW_SHORTCUT));
+        if ui.add(new).clicked() {
+            self.request(PendingAction::New, ctx);
+        }
+        let open = egui::Button::new("Open…").shortcut_text(ctx.format_shortcut(&OPEN_SHORTCUT));
+        if ui.add(open).clicked() {
+            self.request(PendingAction::Open, ctx);
+        }
+        let save = egui::Button::new("Save").shortcut_text(ctx.format_shortcut(&SAVE_SHORTCUT));
+        if ui.add(save).clicked() {
+            self.save_document();
+        }
+        let save_as =
+            egui::Button::new("Save As…").shortcut_text(ctx.format_shortcut(&SAVE_AS_SHORTCUT));
+        if ui.add(save_as).clicked() {
+            self.save_document_as();
+        }
+
+        ui.menu_button("Export", |ui| self.export_menu(ui, ctx));
+
+        ui.separator();
+
+        if ui.button("Quit").clicked() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    pub(super) fn handle_file_shortcuts(&mut self, ctx: &egui::Context) {
+        // check save as first, since the save shortcut also matches with shift held
+        if ctx.input_mut(|i| i.consume_shortcut(&SAVE_AS_SHORTCUT)) {
+            self.save_document_as();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&SAVE_SHORTCUT)) {
+            self.save_document();
+        } else if ctx.input_mut(|i| i.consume_shortcut(&OPEN_SHORTCUT)) {
+            self.request(PendingAction::Open, ctx);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&NEW_SHORTCUT)) {
+            self.request(PendingAction::New, ctx);
+        }
+    }
+
+    /// Performs `action` right away if there are no unsaved changes, and
+    /// otherwise asks the user what to do with them first.
+    fn request(&mut self, action: PendingAction, ctx: &egui::Context) {
+        if self.dirty {
+            self.pending = Some(action);
+        } else {
+            self.perform(action, ctx);
+        }
+    }
+
+    fn perform(&mut self, action: PendingAction, ctx: &egui::Context) {
+        match action {
+            PendingAction::New => {
+                // a new tree is a hole of the sort of the root of the schema
+                let root = self
+                    .schema
+                    .as_ref()
+                    .map_or_else(Tree::hole, |schema| Tree::typed_hole(&schema.root));
+                self.set_document(root, None);
+            }
+            PendingAction::Open => {
+                let Some(path) = pick_open_path("Tree document", document::EXTENSION) else {
+                    return;
+                };
+                match document::read(&path) {
+                    Ok(root) => self.set_document(root, Some(path)),
+                    Err(err) => {
+                        self.show_error(format!("Failed to open {}: {err}", path.display()));
+                    }
+                }
+            }
+            PendingAction::Quit => {
+                self.allow_close = true;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    fn set_document(&mut self, mut root: Tree, file_path: Option<PathBuf>) {
+        self.node_ids = NodeIds::new(&mut root);
+        self.cursor = Cursor::new(root, Index::default());
+        self.history.clear();
+        self.box_sizes.clear();
+        self.outline_rows.clear();
+        self.tidy_layout.clear();
+        self.text_layout.clear();
+        self.violations.clear();
+        self.label_draft = None;
+        self.file_path = file_path;
+        self.dirty = false;
+    }
+
+    /// Saves the tree to its file, or asks where to save it if it has none.
+    /// Returns whether it was saved.
+    fn save_document(&mut self) -> bool {
+        match self.file_path.clone() {
+            Some(path) => self.write_document(path),
+            None => self.save_document_as(),
+        }
+    }
+
+    /// Asks where to save the tree and saves it there. Returns whether it was
+    /// saved.
+    fn save_document_as(&mut self) -> bool {
+        let current = self.file_path.as_deref();
+        let Some(path) = pick_save_path(current, "Tree document", document::EXTENSION) else {
+            return false;
+        };
+        self.write_document(path)
+    }
+
+    fn write_document(&mut self, path: PathBuf) -> bool {
+        match document::write(&path, self.cursor.root()) {
+            Ok(()) => {
+                self.file_path = Some(path);
+                self.dirty = false;
+                true
+            }
+            Err(err) => {
+                self.show_error(format!("Failed to save {}: {err}", path.display()));
+                false
+            }
+        }
+    }
+
+    /// Cancels closing the window while there are unsaved changes, and asks
+    /// the user what to do with them instead.
+    pub(super) fn handle_close_request(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) && self.dirty && !self.allow_close {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.pending = Some(PendingAction::Quit);
+        }
+    }
+
+    pub(super) fn unsaved_changes_dialog(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.pending else {
+            return;
+        };
+        egui::Modal::new(egui::Id::new("unsaved_changes")).show(ctx, |ui| {
+            ui.heading("Unsaved changes");
+            ui.label(format!(
+                "Do you want to save the changes to {}?",
+                self.document_name()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Save").clicked() {
+                    self.pending = None;
+                    if self.save_document() {
+                        self.perform(action, ctx);
+                    }
+                }
+                if ui.button("Don't save").clicked() {
+                    self.pending = None;
+                    self.perform(action, ctx);
+                }
+                if ui.button("Cancel").clicked() {
+                    self.pending = None;
+                }
+            });
+        });
+    }
+
+    /// Shows the name of the document, and whether it has unsaved changes, in
+    /// the title bar.
+    pub(super) fn update_title(&mut self, ctx: &egui::Context) {
+        let title = format!(
+            "{}{} - tree-editor-with-egui",
+            self.document_name(),
+            if self.dirty { "*" } else { "" }
+        );
+        if title != self.title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.title = title;
+        }
+    }
+
+    fn document_name(&self) -> String {
+        self.file_path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Untitled".to_owned())
+    }
+}
+
+/// Asks for a file of the kind called `name` with `extension` to open.
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn pick_open_path(name: &str, extension: &str) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter(name, &[extension])
+        .pick_file()
+}
+
+/// Asks where to save a file of the kind called `name` with `extension`,
+/// suggesting `current`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn pick_save_path(
+    current: Option<&std::path::Path>,
+    name: &str,
+    extension: &str,
+) -> Option<PathBuf> {
+    let mut dialog = rfd::FileDialog::new().add_filter(name, &[extension]);
+    if let Some(directory) = current.and_then(|path| path.parent()) {
+        dialog = dialog.set_directory(directory);
+    }
+    if let Some(name) = current.and_then(|path| path.file_name()) {
+        dialog = dialog.set_file_name(name.to_string_lossy());
+    }
+    dialog.save_file()
+}
+
+// There is no file system on the web.
+
+#[cfg(target_arch = "wasm32")]
+pub(super) fn pick_open_path(_name: &str, _extension: &str) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(super) fn pick_save_path(
+    _current: Option<&std::path::Path>,
+    _name: &str,
+    _extension: &str,
+) -> Option<PathBuf> {
+    None
+}
@@ -0,0 +1,75 @@
+//! Folding nodes, to hide their kids in all of the views.
+
+use super::App;
+use crate::history::Edit;
+use crate::tree::{Folds, Index};
+use egui::{Key, KeyboardShortcut, Modifiers, Ui};
+
+pub(super) const FOLD_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::OpenBracket);
+pub(super) const UNFOLD_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::CloseBracket);
+
+impl App {
+    /// Folds or unfolds the node at `index`. Returns whether it changed, which
+    /// it doesn't for leaves.
+    pub(super) fn set_folded(&mut self, index: &Index, folded: bool) -> bool {
+        let changes = self
+            .cursor
+            .root()
+            .at_index(index)
+            .is_ok_and(|tree| !tree.kids.is_empty() && tree.folded != folded);
+        changes && self.set_folds(vec![(index.clone(), folded)])
+    }
+
+    /// Unfolds the ancestors of the focus, if it is hidden.
+    pub(super) fn reveal_focus(&mut self) {
+        let (root, focus) = self.cursor.root_and_focus();
+        let folds = root.folds_revealing(focus);
+        self.set_folds(folds);
+    }
+
+    /// Folds and unfolds nodes as an edit, so that it can be undone like the
+    /// other changes to the document. Returns whether any node changed.
+    fn set_folds(&mut self, folds: Folds) -> bool {
+        !folds.is_empty() && self.apply_edit(Edit::SetFolds { folds })
+    }
+
+    /// Shows exactly the nodes down to `depth`, and moves the focus out of the
+    /// nodes that are hidden.
+    fn fold_below_depth(&mut self, depth: usize) {
+        let folds = self.cursor.root().folds_below_depth(depth);
+        self.set_folds(folds);
+        let (root, focus) = self.cursor.root_and_focus();
+        if let Some(ancestor) = root.folded_ancestor(focus) {
+            self.cursor.set_focus(ancestor);
+            self.label_draft = None;
+        }
+    }
+
+    pub(super) fn fold_menu(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        let focus = self.cursor.focus().clone();
+        let fold = egui::Button::new("Fold").shortcut_text(ctx.format_shortcut(&FOLD_SHORTCUT));
+        if ui.add(fold).clicked() {
+            self.set_folded(&focus, true);
+        }
+        let unfold =
+            egui::Button::new("Unfold").shortcut_text(ctx.format_shortcut(&UNFOLD_SHORTCUT));
+        if ui.add(unfold).clicked() {
+            self.set_folded(&focus, false);
+        }
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            if ui.button("Fold below depth").clicked() {
+                self.fold_below_depth(self.fold_depth);
+            }
+            ui.add(egui::DragValue::new(&mut self.fold_depth));
+        });
+        if ui.button("Unfold all").clicked() {
+            let folds = self.cursor.root().folds_unfolding_all();
+            self.set_folds(folds);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Jumping between holes, and filling them in with the constructors that the
+//! schema allows there.
+
+use super::App;
+use crate::history::Edit;
+use crate::schema::{Completion, Constructor};
+use crate::tree::Index;
+use egui::{Key, KeyboardShortcut, Modifiers, Ui};
+
+pub(super) const NEXT_HOLE_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::NONE, Key::F3);
+pub(super) const PREV_HOLE_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::SHIFT, Key::F3);
+
+impl App {
+    /// The constructors that the focus can be filled in with: those of the
+    /// sort of the hole, or of the sort expected where it is if it is untyped.
+    /// There are none if the focus isn't a hole or there is no schema.
+    pub(super) fn fill_choices(&mut self) -> Vec<Constructor> {
+        let Some(schema) = &self.schema else {
+            return vec![];
+        };
+        let (root, focus) = self.cursor.root_and_focus();
+        if !root.at_index(focus).is_ok_and(|tree| tree.is_hole()) {
+            return vec![];
+        }
+        schema
+            .completions(root, focus)
+            .into_iter()
+            .filter_map(|completion| match completion {
+                Completion::Fill(constructor) => Some(constructor),
+                Completion::Wrap(..) => None,
+            })
+            .collect()
+    }
+
+    /// Replaces the hole at the focus with a node of `constructor` with holes
+    /// for kids, and moves to the first of them. Returns whether it succeeded.
+    pub(super) fn fill_hole(&mut self, constructor: &Constructor) -> bool {
+        let index = self.cursor.focus().clone();
+        if !self.apply_edit(Edit::Replace {
+            index: index.clone(),
+            tree: constructor.instantiate(),
+        }) {
+            return false;
+        }
+        self.focus_first_hole_in(&index);
+        true
+    }
+
+    /// Moves the focus to the first hole in the subtree at `index`, if it has
+    /// one.
+    pub(super) fn focus_first_hole_in(&mut self, index: &Index) {
+        let mut hole = index.clone();
+        if hole.move_next_hole(self.cursor.root()).is_ok() && index.is_prefix_of(&hole) {
+            self.cursor.set_focus(hole);
+        }
+    }
+
+    pub(super) fn fill_hole_menu(&mut self, ui: &mut Ui) {
+        for constructor in self.fill_choices() {
+            if ui.button(&constructor.label).clicked() {
+                self.fill_hole(&constructor);
+            }
+        }
+    }
+
+    pub(super) fn hole_menu_items(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        let next =
+            egui::Button::new("Next hole").shortcut_text(ctx.format_shortcut(&NEXT_HOLE_SHORTCUT));
+        if ui.add(next).clicked() {
+            let (root, focus) = self.cursor.root_and_focus_mut();
+            let result = focus.move_next_hole(root);
+            if self.check_move(result) {
+                self.reveal_focus();
+            }
+        }
+        let prev = egui::Button::new("Previous hole")
+            .shortcut_text(ctx.format_shortcut(&PREV_HOLE_SHORTCUT));
+        if ui.add(prev).clicked() {
+            let (root, focus) = self.cursor.root_and_focus_mut();
+            let result = focus.move_prev_hole(root);
+            if self.check_move(result) {
+                self.reveal_focus();
+            }
+        }
+        let can_fill = !self.fill_choices().is_empty();
+        ui.add_enabled_ui(can_fill, |ui| {
+            ui.menu_button("Fill hole with", |ui| self.fill_hole_menu(ui));
+        });
+    }
+}
@@ -0,0 +1,120 @@
+//! The inspector: a side panel that shows the focus, and where its attributes
+//! are edited.
+
+use super::App;
+use crate::history::Edit;
+use crate::tree::NodeId;
+use egui::{Key, Ui};
+
+/// What is being typed in the inspector.
+#[derive(Debug, Default)]
+pub struct Drafts {
+    /// The id of the node, the key and the new value of the attribute whose
+    /// value is being edited, if any. The value is set when the field loses
+    /// focus, on the node wherever edits in the meantime moved it.
+    value: Option<(NodeId, String, String)>,
+    /// The key and value of the attribute to add.
+    new_key: String,
+    new_value: String,
+}
+
+impl App {
+    pub(super) fn inspector_panel(&mut self, ui: &mut Ui) {
+        ui.heading("Inspector");
+
+        let focus = self.cursor.focus().clone();
+        let Ok(tree) = self.cursor.focused() else {
+            return;
+        };
+        ui.label(format!("label: {}", tree.label));
+        let focus_id = tree.id;
+        if let Some(id) = focus_id {
+            ui.weak(format!("id: {id}"));
+        }
+        let attrs = tree.attrs.clone();
+
+        ui.separator();
+
+        let drafts = &mut self.attr_drafts;
+        let (root, node_ids) = (self.cursor.root(), &self.node_ids);
+        let mut edit = None;
+        egui::Grid::new("attrs")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for (key, value) in &attrs {
+                    ui.label(key);
+                    let mut text = match &drafts.value {
+                        Some((id, draft_key, draft))
+                            if Some(*id) == focus_id && draft_key == key =>
+                        {
+                            draft.clone()
+                        }
+                        _ => value.clone(),
+                    };
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut text)
+                            .id_salt(key)
+                            .desired_width(120.0),
+                    );
+                    if let (true, Some(id)) = (response.changed(), focus_id) {
+                        drafts.value = Some((id, key.clone(), text));
+                    }
+                    if response.lost_focus() {
+                        // Escape cancels, as it does for labels
+                        let cancelled = ui.input(|i| i.key_pressed(Key::Escape));
+                        if let Some((id, key, value)) = drafts.value.take() {
+                            let index = node_ids.index_of(id).filter(|index| {
+                                root.at_index(index)
+                                    .is_ok_and(|tree| tree.attrs.get(&key) != Some(&value))
+                            });
+                            if let (false, Some(index)) = (cancelled, index) {
+                                edit = Some(Edit::SetAttr {
+                                    index: index.clone(),
+                                    key,
+                                    value: Some(value),
+                                });
+                            }
+                        }
+                    }
+                    if ui
+                        .small_button("✖")
+                        .on_hover_text("Remove attribute")
+                        .clicked()
+                    {
+                        edit = Some(Edit::SetAttr {
+                            index: focus.clone(),
+                            key: key.clone(),
+                            value: None,
+                        });
+                    }
+                    ui.end_row();
+                }
+
+                ui.add(
+                    egui::TextEdit::singleline(&mut drafts.new_key)
+                        .hint_text("key")
+                        .desired_width(60.0),
+                );
+                let value = ui.add(
+                    egui::TextEdit::singleline(&mut drafts.new_value)
+                        .hint_text("value")
+                        .desired_width(120.0),
+                );
+                let entered = value.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                let add = ui.add_enabled(!drafts.new_key.is_empty(), egui::Button::new("Add"));
+                if (add.clicked() || entered) && !drafts.new_key.is_empty() {
+                    edit = Some(Edit::SetAttr {
+                        index: focus.clone(),
+                        key: std::mem::take(&mut drafts.new_key),
+                        value: Some(std::mem::take(&mut drafts.new_value)),
+                    });
+                }
+                ui.end_row();
+            });
+
+        if let Some(edit) = edit {
+            self.apply_edit(edit);
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! The outline view: the tree as an indented list of rows, one per node, like
+//! a file explorer.
+//!
+//! Only the rows in the visible part of the scroll area are laid out. The
+//! list of the rows is kept in a [`RowCache`], and an edit or a fold lists
+//! again only the rows of the subtree that it changed.
+
+use super::App;
+use crate::tree::{Index, Tree};
+use egui::{Key, Modifiers, Ui};
+use std::sync::Arc;
+
+/// How far each depth is indented.
+const INDENT: f32 = 16.0;
+
+/// The rows of the outline, kept between frames.
+#[derive(Debug, Default)]
+pub struct RowCache {
+    /// The indices of the nodes that aren't hidden by folding, in order, or
+    /// `None` if they have to be listed again. Shared, so that the view can
+    /// keep using them while it edits the tree.
+    rows: Option<Arc<Vec<Index>>>,
+}
+
+impl RowCache {
+    pub fn clear(&mut self) {
+        self.rows = None;
+    }
+
+    /// Lists the rows of the subtree of `tree` at `scope` again, after it
+    /// changed.
+    pub fn invalidate(&mut self, tree: &Tree, scope: &Index) {
+        let Some(rows) = &mut self.rows else {
+            return;
+        };
+        // the nodes of a folded subtree have no rows, whatever happened to
+        // them
+        if tree.folded_ancestor(scope).is_some() {
+            return;
+        }
+        let Ok(subtree) = tree.at_index(scope) else {
+            self.rows = None;
+            return;
+        };
+        // the rows are in preorder, which is the order of the steps of their
+        // indices, so those of the subtree are next to each other
+        let rows = Arc::make_mut(rows);
+        let start = rows.partition_point(|index| index.0 < scope.0);
+        let len = rows[start..].partition_point(|index| index.0.starts_with(&scope.0));
+        let mut new_rows = vec![];
+        visible_rows(subtree, &mut scope.clone(), &mut new_rows);
+        rows.splice(start..start + len, new_rows);
+    }
+
+    /// The rows of `tree`, listed again only if they changed.
+    fn rows(&mut self, tree: &Tree) -> Arc<Vec<Index>> {
+        let rows = self.rows.get_or_insert_with(|| {
+            let mut rows = vec![];
+            visible_rows(tree, &mut Index::default(), &mut rows);
+            Arc::new(rows)
+        });
+        Arc::clone(rows)
+    }
+}
+
+impl App {
+    pub(super) fn render_outline(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        let mut moved = self.handle_outline_keys(ctx);
+        moved |= self.handle_keys(ctx);
+
+        let rows = self.outline_rows.rows(self.cursor.root());
+        let row_height = ui.spacing().interact_size.y;
+        let mut scroll_area = egui::ScrollArea::both().auto_shrink([false, false]);
+        if moved {
+            // the row of the focus may not be laid out, so scroll by its
+            // position instead of by its rect
+            let focus = self.cursor.focus();
+            if let Ok(row) = rows.binary_search_by(|index| index.0.cmp(&focus.0)) {
+                let spacing = ui.spacing().item_spacing.y;
+                let top = row as f32 * (row_height + spacing);
+                let offset = top - (ui.available_height() - row_height) / 2.0;
+                scroll_area = scroll_area.vertical_scroll_offset(offset.max(0.0));
+            }
+        }
+        scroll_area.show_rows(ui, row_height, rows.len(), |ui, range| {
+            for index in &rows[range] {
+                self.render_outline_row(ui, index);
+            }
+        });
+    }
+
+    fn render_outline_row(&mut self, ui: &mut Ui, index: &Index) {
+        let Ok(tree) = self.cursor.root().at_index(index) else {
+            return;
+        };
+        let label = tree.label.clone();
+        let has_kids = !tree.kids.is_empty();
+        let is_folded = tree.is_folded();
+        let hidden_count = is_folded.then(|| tree.hidden_count());
+        let is_hole = tree.is_hole();
+        let is_focus = index == self.cursor.focus();
+
+        ui.horizontal(|ui| {
+            ui.set_min_height(ui.spacing().interact_size.y);
+            ui.add_space(index.len() as f32 * INDENT);
+            if has_kids {
+                let triangle = if is_folded { "⏵" } else { "⏷" };
+                let toggle = egui::Button::new(triangle)
+                    .frame(false)
+                    .min_size(egui::vec2(INDENT, 0.0));
+                if ui.add(toggle).clicked() {
+                    self.set_folded(index, !is_folded);
+                }
+            } else {
+                ui.add_space(INDENT);
+            }
+
+            if is_focus && self.label_draft.is_some() {
+                self.render_label_draft(ui);
+            } else {
+                let mut text = egui::RichText::new(label);
+                if is_hole {
+                    text = text.italics().weak();
+                }
+                let response = ui.selectable_label(is_focus, text);
+                if response.clicked() {
+                    self.cursor.set_focus(index.clone());
+                    self.label_draft = None;
+                }
+                if response.double_clicked() {
+                    self.start_label_draft();
+                }
+            }
+
+            if let Some(count) = hidden_count {
+                ui.weak(format!("({count} hidden)"));
+            }
+            if self.violations.is_invalid(index) {
+                ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                    .on_hover_text("Breaks the schema");
+            }
+        });
+    }
+
+    /// Handles the arrow keys, which move between the rows of the outline
+    /// rather than between the boxes. Returns whether the focus moved.
+    fn handle_outline_keys(&mut self, ctx: &egui::Context) -> bool {
+        if self.label_draft.is_some() || self.completion.is_some() || ctx.wants_keyboard_input() {
+            return false;
+        }
+
+        let focus = self.cursor.focus().clone();
+        let (has_kids, is_folded) = self.cursor.focused().map_or((false, false), |tree| {
+            (!tree.kids.is_empty(), tree.is_folded())
+        });
+        if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowUp)) {
+            let result = self.cursor.move_prev();
+            let moved = self.check_move(result);
+            // the row above stands for the node before the focus, if that is
+            // hidden
+            let (root, focus) = self.cursor.root_and_focus();
+            if let Some(ancestor) = root.folded_ancestor(focus) {
+                self.cursor.set_focus(ancestor);
+            }
+            moved
+        } else if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowDown)) {
+            // skip over the kids of a folded focus
+            let result = if is_folded {
+                self.cursor.move_up_until_right()
+            } else {
+                self.cursor.move_next()
+            };
+            self.check_move(result)
+        } else if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowLeft)) {
+            if has_kids && !is_folded {
+                self.set_folded(&focus, true);
+                false
+            } else {
+                let result = self.cursor.move_up();
+                self.check_move(result)
+            }
+        } else if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowRight)) {
+            if is_folded {
+                self.set_folded(&focus, false);
+                false
+            } else if has_kids {
+                let result = self.cursor.move_down(0);
+                self.check_move(result)
+            } else {
+                false
+            }
+        } else {
+            false
+        }
+    }
+}
+
+/// Pushes the indices of the rows of `tree` and of its kids that aren't
+/// hidden by folding onto `rows`, in order.
+fn visible_rows(tree: &Tree, index: &mut Index, rows: &mut Vec<Index>) {
+    rows.push(index.clone());
+    if tree.is_folded() {
+        return;
+    }
+    for (i, kid) in tree.kids.iter().enumerate() {
+        index.push(i);
+        visible_rows(kid, index, rows);
+        index.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::sexpr;
+
+    fn all_rows(tree: &Tree) -> Vec<Index> {
+        let mut rows = vec![];
+        visible_rows(tree, &mut Index::default(), &mut rows);
+        rows
+    }
+
+    #[test]
+    fn outline_row_cache_test1() {
+        let mut tree = sexpr::parse("(A (B D (E F)) (C G))").unwrap();
+        let mut cache = RowCache::default();
+        assert_eq!(cache.rows(&tree).len(), 7);
+
+        tree.kids[0].folded = true;
+        cache.invalidate(&tree, &Index(vec![0]));
+        assert_eq!(*cache.rows(&tree), all_rows(&tree));
+
+        // an edit inside of the folded node changes no rows
+        tree.kids[0].kids.push(Tree::mk("H", &[]));
+        cache.invalidate(&tree, &Index(vec![0, 2]));
+        assert_eq!(*cache.rows(&tree), all_rows(&tree));
+
+        tree.kids[0].folded = false;
+        cache.invalidate(&tree, &Index(vec![0]));
+        tree.kids.insert(1, Tree::mk("I", &[]));
+        cache.invalidate(&tree, &Index::default());
+        tree.kids[2].kids.clear();
+        cache.invalidate(&tree, &Index(vec![2]));
+        assert_eq!(*cache.rows(&tree), all_rows(&tree));
+        assert_eq!(cache.rows(&tree).len(), 8);
+    }
+}
@@ -0,0 +1,65 @@
+//! Typing in an expression with the grammar of the schema, and putting the
+//! tree it parses to at the focus.
+
+use super::App;
+use crate::grammar::{self, Grammar};
+use crate::history::Edit;
+use crate::tree::sexpr;
+use std::borrow::Cow;
+
+impl App {
+    /// The grammar of the schema, or the example grammar if there is no
+    /// schema.
+    pub(super) fn grammar(&self) -> Cow<'_, Grammar> {
+        self.schema.as_ref().map_or_else(
+            || Cow::Owned(grammar::example()),
+            |schema| Cow::Borrowed(&schema.grammar),
+        )
+    }
+
+    /// Shows the window for typing in an expression to insert, if it is open.
+    pub(super) fn parse_window(&mut self, ctx: &egui::Context) {
+        let Some(mut draft) = self.expr_draft.take() else {
+            return;
+        };
+        let mut open = true;
+        let mut inserted = None;
+        let grammar = self.grammar();
+        egui::Window::new("Parse expression")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label("Replaces the focus with the tree of the expression:");
+                ui.add(
+                    egui::TextEdit::multiline(&mut draft)
+                        .code_editor()
+                        .desired_rows(4),
+                );
+                let parsed = grammar.parse(&draft);
+                ui.label(
+                    egui::RichText::new(sexpr::to_string(&parsed.tree))
+                        .monospace()
+                        .weak(),
+                );
+                for err in &parsed.errors {
+                    ui.colored_label(ui.visuals().warn_fg_color, err.to_string());
+                }
+                if !parsed.errors.is_empty() {
+                    ui.label("Holes are put where the expression couldn't be parsed.");
+                }
+                if ui.button("Insert").clicked() {
+                    inserted = Some(parsed.tree);
+                }
+            });
+        if let Some(tree) = inserted {
+            let index = self.cursor.focus().clone();
+            if self.apply_edit(Edit::Replace {
+                index: index.clone(),
+                tree,
+            }) {
+                self.focus_first_hole_in(&index);
+            }
+        } else if open {
+            self.expr_draft = Some(draft);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Saving and restoring the [`App`] state, tagged with a schema version.
+//!
+//! The state is stored as JSON under [`eframe::APP_KEY`] as
+//! `{ "version": VERSION, "app": ... }`. When loading state saved by an older
+//! version, the migrations from that version up to [`VERSION`] are applied to
+//! the JSON before it is deserialized, so that changes to the shape of the
+//! state don't silently discard it.
+
+use super::App;
+use crate::tree::{Index, NodeIds, Tree};
+
+/// The current version of the schema of the saved state.
+pub const VERSION: u32 = 1;
+
+/// Where state that couldn't be loaded is kept, so that saving doesn't
+/// overwrite it for good.
+const BACKUP_KEY: &str = "app-backup";
+
+/// `MIGRATIONS[v]` migrates the `app` of the state saved with version `v` to
+/// version `v + 1`.
+const MIGRATIONS: [fn(serde_json::Value) -> Result<serde_json::Value, String>; VERSION as usize] =
+    [migrate_v0_to_v1];
+
+/// Version 0 is the unversioned RON state written by `eframe::set_value`
+/// before the state was versioned. It has the same fields as version 1.
+#[expect(
+    clippy::unnecessary_wraps,
+    reason = "all migrations have the same signature"
+)]
+fn migrate_v0_to_v1(app: serde_json::Value) -> Result<serde_json::Value, String> {
+    Ok(app)
+}
+
+/// The state as it was saved before it was versioned.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct StateV0 {
+    root: Tree,
+    focus: Index,
+}
+
+#[derive(serde::Serialize)]
+struct VersionedRef<'a> {
+    version: u32,
+    app: &'a App,
+}
+
+#[derive(serde::Deserialize)]
+struct Versioned {
+    version: u32,
+    app: serde_json::Value,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Json(serde_json::Error),
+    /// The state is neither versioned JSON nor unversioned RON.
+    Unreadable,
+    /// The state was saved by a newer version of the app.
+    UnknownVersion(u32),
+    Migration {
+        version: u32,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Json(err) => write!(f, "invalid saved state: {err}"),
+            Self::Unreadable => write!(f, "saved state is in an unknown format"),
+            Self::UnknownVersion(version) => write!(
+                f,
+                "saved state has version {version}, but the latest known version is {VERSION}"
+            ),
+            Self::Migration { version, message } => write!(
+                f,
+                "failed to migrate saved state from version {version}: {message}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// Loads the app state from `storage`, falling back to the default state if
+/// there is none or it can't be loaded.
+pub fn load(storage: &dyn eframe::Storage) -> App {
+    let Some(string) = storage.get_string(eframe::APP_KEY) else {
+        return App::default();
+    };
+
+    let result = if serde_json::from_str::<serde_json::Value>(&string).is_ok() {
+        from_json(&string)
+    } else {
+        eframe::get_value::<StateV0>(storage, eframe::APP_KEY)
+            .ok_or(Error::Unreadable)
+            .and_then(from_v0)
+    };
+
+    match result {
+        Ok(app) => app,
+        Err(err) => {
+            log::error!("discarding saved state: {err}");
+            App {
+                discarded_state: Some(string),
+                ..App::default()
+            }
+        }
+    }
+}
+
+/// Saves the app state to `storage`.
+pub fn save(app: &App, storage: &mut dyn eframe::Storage) {
+    if let Some(discarded_state) = &app.discarded_state {
+        storage.set_string(BACKUP_KEY, discarded_state.clone());
+    }
+    match to_json(app) {
+        Ok(string) => storage.set_string(eframe::APP_KEY, string),
+        Err(err) => log::error!("failed to save state: {err}"),
+    }
+}
+
+fn to_json(app: &App) -> Result<String, Error> {
+    Ok(serde_json::to_string(&VersionedRef {
+        version: VERSION,
+        app,
+    })?)
+}
+
+fn from_json(string: &str) -> Result<App, Error> {
+    let Versioned { version, app } = serde_json::from_str(string)?;
+    migrate(version, app)
+}
+
+fn from_v0(state: StateV0) -> Result<App, Error> {
+    migrate(0, serde_json::to_value(state)?)
+}
+
+/// Migrates `app` from `version` to [`VERSION`] and deserializes it.
+fn migrate(version: u32, mut app: serde_json::Value) -> Result<App, Error> {
+    if version > VERSION {
+        return Err(Error::UnknownVersion(version));
+    }
+    for (v, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        app = migration(app).map_err(|message| Error::Migration {
+            version: v as u32,
+            message,
+        })?;
+    }
+
+    let mut app: App = serde_json::from_value(app)?;
+    let (root, focus) = app.cursor.root_and_focus();
+    if !root.is_index_in_bounds(focus) {
+        log::warn!("resetting saved focus {focus:?}: out of bounds");
+        app.cursor.set_focus(Index::default());
+    }
+    app.node_ids = NodeIds::new(app.cursor.root_mut());
+    Ok(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Cursor;
+
+    #[test]
+    fn persistence_round_trip_test1() {
+        let mut app = App {
+            cursor: Cursor::new(Tree::mk("A", &[Tree::mk("B", &[])]), Index(vec![0])),
+            ..App::default()
+        };
+        app.node_ids = NodeIds::new(app.cursor.root_mut());
+
+        let mut app_new = from_json(&to_json(&app).unwrap()).unwrap();
+
+        assert_eq!(app_new.cursor.root(), app.cursor.root());
+        assert_eq!(app_new.cursor.focus(), app.cursor.focus());
+        assert_eq!(app_new.templates, app.templates);
+    }
+
+    #[test]
+    fn persistence_migrate_v0_test1() {
+        let state = StateV0 {
+            root: Tree::mk("A", &[Tree::mk("B", &[])]),
+            focus: Index(vec![0]),
+        };
+
+        let mut app = from_v0(state).unwrap();
+
+        assert_eq!(crate::tree::sexpr::to_string(app.cursor.root()), "(A B)");
+        assert_eq!(app.cursor.focus(), &Index(vec![0]));
+        // the nodes are given ids when they are loaded
+        let id = app.cursor.root().kids[0].id.unwrap();
+        assert_eq!(app.node_ids.index_of(id), Some(&Index(vec![0])));
+    }
+
+    #[test]
+    fn persistence_focus_out_of_bounds_test1() {
+        let json = r#"{"version":1,"app":{"root":{"label":"A","kids":[]},"focus":[3]}}"#;
+
+        let app = from_json(json).unwrap();
+
+        assert_eq!(app.cursor.focus(), &Index::default());
+    }
+
+    #[test]
+    fn persistence_unknown_version_test1() {
+        let json = format!(r#"{{"version":{},"app":{{}}}}"#, VERSION + 1);
+
+        assert!(matches!(
+            from_json(&json),
+            Err(Error::UnknownVersion(version)) if version == VERSION + 1
+        ));
+    }
+}
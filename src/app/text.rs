@@ -0,0 +1,230 @@
+//! The tree printed as text with the grammar of the schema, with the text of
+//! the focus highlighted: in a panel next to the other views, and as a view
+//! of its own where the text is edited structurally.
+//!
+//! In the view, selecting text selects the smallest subtree that has all of
+//! it, double-clicking a node edits its label in place, and the arrow keys
+//! move the focus as in the other views.
+
+use super::App;
+use crate::grammar::DocCache;
+use crate::pretty::Layout;
+use crate::tree::{self, Index};
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Galley, Pos2, Rect, Response, Sense, Ui};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// The fewest columns that the text is fitted into, however narrow the panel
+/// is.
+const MIN_WIDTH: usize = 20;
+
+/// The tree as printed, kept between frames.
+#[derive(Debug, Default)]
+pub struct LayoutCache {
+    /// By the width in columns that the tree was printed in.
+    layouts: HashMap<usize, Arc<Layout>>,
+    /// The documents of the nodes, which don't depend on the width.
+    docs: DocCache,
+}
+
+impl LayoutCache {
+    pub fn clear(&mut self) {
+        self.layouts.clear();
+        self.docs.clear();
+    }
+
+    /// Forgets the text, and the documents of the subtree at `index` and of
+    /// its ancestors.
+    pub fn invalidate(&mut self, index: &Index) {
+        self.layouts.clear();
+        self.docs.invalidate(index);
+    }
+}
+
+impl App {
+    /// The tree printed with the grammar, to fit into the width of `ui`,
+    /// printed again only if it changed.
+    fn layout_text(&mut self, ui: &Ui) -> Arc<Layout> {
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        let glyph_width = ui.fonts(|fonts| fonts.glyph_width(&font_id, ' '));
+        let width = ((ui.available_width() / glyph_width) as usize).max(MIN_WIDTH);
+        let cache = &mut self.text_layout;
+        if let Some(layout) = cache.layouts.get(&width) {
+            return layout.clone();
+        }
+        // the panel and the view are shown in a width each, so any more are
+        // left over from resizing
+        if cache.layouts.len() >= 2 {
+            cache.layouts.clear();
+        }
+        let grammar = self.grammar().into_owned();
+        let layout = grammar.print_cached(self.cursor.root(), width, &mut self.text_layout.docs);
+        let layout = Arc::new(layout);
+        self.text_layout.layouts.insert(width, layout.clone());
+        layout
+    }
+
+    /// Shows the tree as text. Clicking the text of a node focuses it.
+    pub(super) fn text_panel(&mut self, ui: &mut Ui) {
+        let layout = self.layout_text(ui);
+        let text = Text::show(ui, &layout, self.cursor.focus(), Sense::click());
+        if !text.response.clicked() {
+            return;
+        }
+        let clicked = text
+            .response
+            .interact_pointer_pos()
+            .and_then(|pos| layout.index_at(text.offset_at(pos)));
+        if let Some(index) = clicked {
+            self.cursor.set_focus(index.clone());
+            self.label_draft = None;
+            self.reveal_focus();
+        }
+    }
+
+    pub(super) fn render_text_view(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        // handle the keys first, so that the text is printed for the tree as
+        // it is after the edits that they make
+        let moved_by_keys = self.handle_keys(ctx);
+
+        let layout = self.layout_text(ui);
+        let text = Text::show(ui, &layout, self.cursor.focus(), Sense::click_and_drag());
+        let response = &text.response;
+
+        let mut moved = false;
+        if let Some(pos) = response.interact_pointer_pos() {
+            let offset = text.offset_at(pos);
+            // the selection snaps to the smallest subtree that has all of it
+            let selected = if response.dragged() {
+                let origin = ctx.input(|i| i.pointer.press_origin()).unwrap_or(pos);
+                let origin = text.offset_at(origin);
+                // past the end of the text is the end of the last line
+                let end = (origin.max(offset) + 1).min(layout.text.len());
+                layout.index_spanning(origin.min(offset)..end)
+            } else if response.clicked() {
+                layout.index_at(offset)
+            } else {
+                None
+            };
+            if let Some(index) = selected {
+                if index != self.cursor.focus() {
+                    self.cursor.set_focus(index.clone());
+                    self.label_draft = None;
+                    moved = true;
+                }
+            }
+            if response.double_clicked() {
+                self.start_label_draft();
+            }
+        }
+
+        if self.label_draft.is_some() {
+            if let Some(range) = layout.label_of(self.cursor.focus()) {
+                let rect = text.rect_of(range).expand2(egui::vec2(40.0, 2.0));
+                let mut ui = ui.new_child(egui::UiBuilder::new().max_rect(rect));
+                self.render_label_draft(&mut ui);
+            }
+        }
+
+        if moved {
+            self.reveal_focus();
+        }
+        // the text that was clicked is in view already
+        if moved_by_keys {
+            if let Some(range) = layout.span_of(self.cursor.focus()) {
+                ui.scroll_to_rect(text.rect_of(range), Some(egui::Align::Center));
+            }
+        }
+    }
+}
+
+/// The text of a [`Layout`] as it is shown.
+struct Text<'a> {
+    layout: &'a Layout,
+    galley: Arc<Galley>,
+    rect: Rect,
+    response: Response,
+}
+
+impl<'a> Text<'a> {
+    /// Shows the text of `layout`, with the span of `focus` highlighted and
+    /// holes weak.
+    fn show(ui: &mut Ui, layout: &'a Layout, focus: &Index, sense: Sense) -> Self {
+        let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+        let visuals = ui.visuals();
+        let text = layout.text.as_str();
+        let focus = layout.span_of(focus).unwrap_or(0..0);
+        let holes: Vec<Range<usize>> = layout
+            .labels
+            .iter()
+            .filter(|span| tree::is_hole_label(&text[span.range.clone()]))
+            .map(|span| span.range.clone())
+            .collect();
+
+        // split the text where its format changes
+        let mut bounds = vec![0, text.len(), focus.start, focus.end];
+        bounds.extend(holes.iter().flat_map(|hole| [hole.start, hole.end]));
+        bounds.sort_unstable();
+        bounds.dedup();
+        let mut job = LayoutJob::default();
+        for section in bounds.windows(2) {
+            let (start, end) = (section[0], section[1]);
+            let is_focus = focus.start <= start && end <= focus.end;
+            let is_hole = holes
+                .iter()
+                .any(|hole| hole.start <= start && end <= hole.end);
+            let mut format = TextFormat::simple(font_id.clone(), visuals.text_color());
+            if is_focus {
+                format.background = visuals.selection.bg_fill;
+                format.color = visuals.selection.stroke.color;
+            }
+            if is_hole {
+                format.color = visuals.weak_text_color();
+                format.italics = true;
+            }
+            job.append(&text[start..end], 0.0, format);
+        }
+        let galley = ui.painter().layout_job(job);
+
+        let (rect, response) = ui.allocate_exact_size(galley.size(), sense);
+        ui.painter()
+            .galley(rect.min, galley.clone(), ui.visuals().text_color());
+        let response = response.on_hover_cursor(egui::CursorIcon::Text);
+        Self {
+            layout,
+            galley,
+            rect,
+            response,
+        }
+    }
+
+    /// The offset in bytes of the character at `pos`.
+    fn offset_at(&self, pos: Pos2) -> usize {
+        let pos = pos - self.rect.min;
+        let cursor = self.galley.cursor_from_pos(pos);
+        // the cursor nearest to `pos` may be after the character there
+        let chars = if pos.x < self.galley.pos_from_cursor(cursor).min.x {
+            cursor.index.saturating_sub(1)
+        } else {
+            cursor.index
+        };
+        let text = &self.layout.text;
+        text.char_indices()
+            .nth(chars)
+            .map_or(text.len(), |(offset, _)| offset)
+    }
+
+    /// The rectangle around the text in `range`, which is assumed to be on one
+    /// line, or around the first line of it otherwise.
+    fn rect_of(&self, range: Range<usize>) -> Rect {
+        let text = &self.layout.text;
+        let start = text[..range.start].chars().count();
+        let end = start + text[range].chars().count();
+        let start = self.galley.pos_from_cursor(egui::text::CCursor::new(start));
+        let end = self.galley.pos_from_cursor(egui::text::CCursor::new(end));
+        let end = if end.min.y > start.min.y { start } else { end };
+        start.union(end).translate(self.rect.min.to_vec2())
+    }
+}
@@ -0,0 +1,178 @@
+//! The tidy view: the tree as a top-down node-link diagram, laid out by
+//! [`Layout::tidy`] and drawn with the painter.
+
+use super::App;
+use crate::tree::export::svg;
+use crate::tree::layout::{Layout, Node, ShapeCache, Spacing};
+use crate::tree::{Index, Tree};
+use egui::{Align2, Color32, Rect, Sense, Stroke, StrokeKind, Ui, Vec2};
+use std::fmt::Display;
+use std::sync::Arc;
+
+/// The space between the label of a node and its outline.
+const PADDING: Vec2 = Vec2::new(6.0, 3.0);
+
+fn to_vec2([x, y]: [f32; 2]) -> Vec2 {
+    Vec2::new(x, y)
+}
+
+/// The text of the node of `tree`, which says how many nodes are hidden if it
+/// is folded.
+fn node_text<L: Display>(tree: &Tree<L>) -> String {
+    if tree.is_folded() {
+        format!("{} … {} hidden", tree.label, tree.hidden_count())
+    } else {
+        tree.label.to_string()
+    }
+}
+
+/// The layout of the tree, kept between frames.
+#[derive(Debug, Default)]
+pub struct LayoutCache {
+    /// Shared, so that the view can keep using it while it edits the tree.
+    layout: Option<Arc<Layout>>,
+    /// The shapes of the subtrees, which the layout is placed from.
+    shapes: ShapeCache,
+    /// The font that the labels were measured with.
+    font_id: Option<egui::FontId>,
+}
+
+impl LayoutCache {
+    pub fn clear(&mut self) {
+        self.layout = None;
+        self.shapes.clear();
+    }
+
+    /// Forgets the layout, and the shapes of the subtree at `index` and of
+    /// its ancestors.
+    pub fn invalidate(&mut self, index: &Index) {
+        self.layout = None;
+        self.shapes.invalidate(index);
+    }
+}
+
+impl App {
+    /// The layout of the tree, laid out again only if it changed.
+    fn tidy_layout(&mut self, ui: &Ui, font_id: &egui::FontId) -> Arc<Layout> {
+        let cache = &mut self.tidy_layout;
+        if cache.font_id.as_ref() != Some(font_id) {
+            cache.clear();
+            cache.font_id = Some(font_id.clone());
+        }
+        let root = self.cursor.root();
+        let shapes = &mut cache.shapes;
+        let layout = cache.layout.get_or_insert_with(|| {
+            let layout = ui.fonts(|fonts| {
+                let spacing = Spacing {
+                    node_height: fonts.row_height(font_id) + 2.0 * PADDING.y,
+                    sibling: 12.0,
+                    level: 32.0,
+                };
+                Layout::tidy_cached(
+                    root,
+                    |tree| {
+                        let galley =
+                            fonts.layout_no_wrap(node_text(tree), font_id.clone(), Color32::WHITE);
+                        galley.size().x + 2.0 * PADDING.x
+                    },
+                    &spacing,
+                    shapes,
+                )
+            });
+            Arc::new(layout)
+        });
+        Arc::clone(layout)
+    }
+
+    pub(super) fn render_tidy_tree(&mut self, ui: &mut Ui, ctx: &egui::Context) {
+        // handle the keys first, so that the tree is laid out as it is after
+        // the edits that they make
+        let mut moved = self.handle_keys(ctx);
+
+        let font_id = egui::TextStyle::Button.resolve(ui.style());
+        let layout = self.tidy_layout(ui, &font_id);
+
+        let (response, painter) = ui.allocate_painter(to_vec2(layout.size), Sense::click());
+        let origin = response.rect.min;
+        let node_rect =
+            |node: &Node| Rect::from_min_size(origin + to_vec2(node.position), to_vec2(node.size));
+        let node_at = |pos: egui::Pos2| {
+            let point = pos - origin;
+            layout.node_at([point.x, point.y])
+        };
+
+        let visuals = ui.visuals();
+        let edge_stroke = visuals.widgets.noninteractive.fg_stroke;
+        let hovered = response.hover_pos().and_then(node_at);
+        // only the nodes and edges in the visible part of the scroll area
+        // are drawn
+        let visible = ui.clip_rect().translate(-origin.to_vec2());
+        let [min, max] = [visible.min, visible.max].map(|pos| [pos.x, pos.y]);
+        for (parent, kid) in layout.edges_in(min, max) {
+            painter.line_segment(
+                [
+                    node_rect(parent).center_bottom(),
+                    node_rect(kid).center_top(),
+                ],
+                edge_stroke,
+            );
+        }
+        let (root, focus) = self.cursor.root_and_focus();
+        for node in layout.nodes_in(min, max) {
+            let rect = node_rect(node);
+            let widget = if hovered == Some(node) {
+                &visuals.widgets.hovered
+            } else {
+                &visuals.widgets.inactive
+            };
+            // the same focus highlight as the boxes view
+            let stroke = if node.index == *focus {
+                Stroke::new(svg::Style::DEFAULT.stroke_width, Color32::RED)
+            } else if self.violations.is_invalid(&node.index) {
+                Stroke::new(svg::Style::DEFAULT.stroke_width, visuals.warn_fg_color)
+            } else {
+                widget.bg_stroke
+            };
+            let tree = root.at_index_unsafe(&node.index);
+            // holes are sunken, so that they stand out as yet to be filled in
+            let (fill, text_color) = if tree.is_hole() {
+                (visuals.extreme_bg_color, visuals.weak_text_color())
+            } else {
+                (widget.weak_bg_fill, widget.text_color())
+            };
+            painter.rect(rect, 4.0, fill, stroke, StrokeKind::Inside);
+            painter.text(
+                rect.center(),
+                Align2::CENTER_CENTER,
+                node_text(tree),
+                font_id.clone(),
+                text_color,
+            );
+        }
+
+        if let Some(node) = response.interact_pointer_pos().and_then(node_at) {
+            if response.clicked() {
+                self.cursor.set_focus(node.index.clone());
+                self.label_draft = None;
+                moved = true;
+            }
+            if response.double_clicked() {
+                self.start_label_draft();
+            }
+        }
+
+        if self.label_draft.is_some() {
+            if let Some(node) = layout.get(self.cursor.focus()) {
+                let rect = node_rect(node);
+                let mut ui = ui.new_child(egui::UiBuilder::new().max_rect(rect));
+                self.render_label_draft(&mut ui);
+            }
+        }
+
+        if moved {
+            if let Some(node) = layout.get(self.cursor.focus()) {
+                ui.scroll_to_rect(node_rect(node), Some(egui::Align::Center));
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Checking the tree against a [`Schema`], and showing where it breaks it.
+
+use super::App;
+use crate::schema::{self, Schema, Violation};
+use crate::tree::{self, Index};
+use egui::Ui;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// The violations of the schema by the tree, kept until the next edit.
+#[derive(Debug, Default)]
+pub struct Violations {
+    /// Whether the tree was checked since the last edit.
+    checked: bool,
+    list: Vec<Violation>,
+    invalid: HashSet<Index>,
+}
+
+impl Violations {
+    /// Forgets the violations, so that the tree is checked again.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Whether the node at `index` breaks the schema.
+    pub fn is_invalid(&self, index: &Index) -> bool {
+        self.invalid.contains(index)
+    }
+}
+
+impl App {
+    /// Checks the tree again, if it or the schema changed since it was last
+    /// checked.
+    pub(super) fn update_violations(&mut self) {
+        if self.violations.checked {
+            return;
+        }
+        let list = self
+            .schema
+            .as_ref()
+            .map_or_else(Vec::new, |schema| schema.validate(self.cursor.root()));
+        let invalid = list
+            .iter()
+            .map(|violation| violation.index.clone())
+            .collect();
+        self.violations = Violations {
+            checked: true,
+            list,
+            invalid,
+        };
+    }
+
+    fn set_schema(&mut self, schema: Option<Schema>) {
+        self.schema = schema;
+        // the text is printed with the grammar of the schema
+        self.text_layout.clear();
+        self.violations.clear();
+    }
+
+    pub(super) fn schema_menu(&mut self, ui: &mut Ui) {
+        if ui
+            .add_enabled(self.schema.is_some(), egui::Button::new("No schema"))
+            .clicked()
+        {
+            self.set_schema(None);
+        }
+        if ui.button("Example schema").clicked() {
+            self.set_schema(Some(schema::example()));
+        }
+        if !cfg!(target_arch = "wasm32") && ui.button("Open schema…").clicked() {
+            if let Some(path) = super::file::pick_open_path("Schema", "json") {
+                self.open_schema(&path);
+            }
+        }
+    }
+
+    fn open_schema(&mut self, path: &Path) {
+        let schema = std::fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| serde_json::from_str(&text).map_err(|err| err.to_string()))
+            .and_then(|schema: Schema| {
+                // the labels of typed holes of other sorts wouldn't be holes
+                match schema
+                    .sorts
+                    .iter()
+                    .find(|sort| !tree::is_sort_name(&sort.name))
+                {
+                    Some(sort) => Err(format!("the sort name {:?} isn't capitalized", sort.name)),
+                    None => Ok(schema),
+                }
+            });
+        match schema {
+            Ok(schema) => self.set_schema(Some(schema)),
+            Err(err) => self.show_error(format!("Failed to open {}: {err}", path.display())),
+        }
+    }
+
+    /// Lists the violations. Clicking one focuses the node that breaks the
+    /// schema.
+    pub(super) fn violations_panel(&mut self, ui: &mut Ui) {
+        let violations = &self.violations;
+        if violations.list.is_empty() {
+            ui.label("The tree follows the schema.");
+            return;
+        }
+
+        let mut clicked = None;
+        for violation in &violations.list {
+            let text = format!("⚠ {:?}: {violation}", violation.index);
+            let label = ui.selectable_label(violation.index == *self.cursor.focus(), text);
+            if label.clicked() {
+                clicked = Some(violation.index.clone());
+            }
+        }
+        if let Some(index) = clicked {
+            self.cursor.set_focus(index);
+            self.label_draft = None;
+            self.reveal_focus();
+        }
+    }
+}
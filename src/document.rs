@@ -0,0 +1,126 @@
+//! The document format for storing a [`Tree`] in a file.
+//!
+//! A document is JSON of the form
+//! `{ "format": FORMAT, "version": VERSION, "root": ... }`.
+
+use crate::tree::Tree;
+
+/// The file extension of documents.
+pub const EXTENSION: &str = "json";
+
+/// Identifies a JSON file as a document.
+const FORMAT: &str = "tree-editor-with-egui";
+
+/// The current version of the document format.
+const VERSION: u32 = 1;
+
+#[derive(serde::Serialize)]
+struct DocumentRef<'a> {
+    format: &'a str,
+    version: u32,
+    root: &'a Tree,
+}
+
+#[derive(serde::Deserialize)]
+struct Document {
+    format: String,
+    version: u32,
+    root: Tree,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// The file is JSON, but not a document.
+    WrongFormat(String),
+    /// The document was written by a newer version of the app.
+    UnknownVersion(u32),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Json(err) => write!(f, "invalid document: {err}"),
+            Self::WrongFormat(format) => {
+                write!(f, "expected a {FORMAT:?} document, but got {format:?}")
+            }
+            Self::UnknownVersion(version) => write!(
+                f,
+                "document has version {version}, but the latest known version is {VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+pub fn to_string(root: &Tree) -> Result<String, Error> {
+    Ok(serde_json::to_string_pretty(&DocumentRef {
+        format: FORMAT,
+        version: VERSION,
+        root,
+    })?)
+}
+
+pub fn from_str(string: &str) -> Result<Tree, Error> {
+    let document: Document = serde_json::from_str(string)?;
+    if document.format != FORMAT {
+        return Err(Error::WrongFormat(document.format));
+    }
+    if document.version > VERSION {
+        return Err(Error::UnknownVersion(document.version));
+    }
+    Ok(document.root)
+}
+
+pub fn read(path: &std::path::Path) -> Result<Tree, Error> {
+    from_str(&std::fs::read_to_string(path)?)
+}
+
+pub fn write(path: &std::path::Path, root: &Tree) -> Result<(), Error> {
+    Ok(std::fs::write(path, to_string(root)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_round_trip_test1() {
+        let mut tree = Tree::mk("A", &[Tree::mk("B", &[]), Tree::hole()]);
+        tree.folded = true;
+
+        assert_eq!(from_str(&to_string(&tree).unwrap()).unwrap(), tree);
+    }
+
+    #[test]
+    fn document_wrong_format_test1() {
+        let string = r#"{"format":"other","version":1,"root":{"label":"A","kids":[]}}"#;
+
+        assert!(matches!(from_str(string), Err(Error::WrongFormat(_))));
+    }
+
+    #[test]
+    fn document_unknown_version_test1() {
+        let string = format!(
+            r#"{{"format":"{FORMAT}","version":{},"root":{{"label":"A","kids":[]}}}}"#,
+            VERSION + 1
+        );
+
+        assert!(matches!(from_str(&string), Err(Error::UnknownVersion(_))));
+    }
+}
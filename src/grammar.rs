@@ -0,0 +1,581 @@
+//! Parsing text into a [`Tree`] with a grammar of operators, for typing in
+//! whole expressions at once, and printing trees back to text.
+//!
+//! The text is made of atoms, such as names, numbers and holes, which become
+//! leaves; applications `f(a, b)`, which become a node `f` with a kid for each
+//! argument; parentheses for grouping; the prefix and infix operators of the
+//! [`Grammar`], which become nodes with the label of the operator; and its
+//! [`Mixfix`] constructors, such as `let x = 1`, whose layout templates say
+//! which tokens go around their kids. Infix operators are parsed by
+//! precedence climbing.
+//!
+//! Parsing never fails outright. Where an operand is missing, a hole is put in
+//! its place, and text that can't be parsed is skipped, with an [`Error`] for
+//! each of them.
+
+mod print;
+
+pub use print::DocCache;
+
+use crate::tree::Tree;
+use crate::tree::sexpr::Position;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Assoc {
+    /// `a - b - c` is `(a - b) - c`.
+    Left,
+    /// `a ^ b ^ c` is `a ^ (b ^ c)`.
+    Right,
+}
+
+/// An infix operator, such as `a + b`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Infix {
+    pub symbol: String,
+    /// The label of the nodes that the operator makes.
+    pub label: String,
+    /// Operators with higher precedence bind tighter.
+    pub precedence: u32,
+    pub assoc: Assoc,
+}
+
+impl Infix {
+    pub fn mk(symbol: &str, label: &str, precedence: u32, assoc: Assoc) -> Self {
+        Self {
+            symbol: symbol.to_owned(),
+            label: label.to_owned(),
+            precedence,
+            assoc,
+        }
+    }
+}
+
+/// A prefix operator, such as `-a`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Prefix {
+    pub symbol: String,
+    pub label: String,
+    /// The operand of the operator extends over the infix operators with at
+    /// least this precedence.
+    pub precedence: u32,
+}
+
+impl Prefix {
+    pub fn mk(symbol: &str, label: &str, precedence: u32) -> Self {
+        Self {
+            symbol: symbol.to_owned(),
+            label: label.to_owned(),
+            precedence,
+        }
+    }
+}
+
+/// A part of the layout template of a [`Mixfix`] constructor. Tokens and
+/// kids are separated by spaces, except where a [`Part::Line`] is between
+/// them.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Part {
+    /// A keyword or a symbol, such as `let` or `=`.
+    Token(String),
+    /// The next kid.
+    Kid,
+    /// A space, or a line break if the node doesn't fit on its line.
+    Line,
+    /// Parts whose line breaks are indented further.
+    Nest(Vec<Part>),
+}
+
+/// A constructor that is written with tokens around its kids, such as
+/// `let x = 1`.
+///
+/// The template has to start with a token, which is how the parser knows the
+/// constructor. A kid is parsed as a whole expression, so it extends over any
+/// infix operators after it; if the template ends with a kid, the node is put
+/// in parentheses where it is an operand.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Mixfix {
+    pub label: String,
+    pub parts: Vec<Part>,
+}
+
+impl Mixfix {
+    pub fn mk(label: &str, parts: Vec<Part>) -> Self {
+        Self {
+            label: label.to_owned(),
+            parts,
+        }
+    }
+
+    /// The number of kids of the nodes of the constructor.
+    pub fn kid_count(&self) -> usize {
+        fn count(parts: &[Part]) -> usize {
+            parts
+                .iter()
+                .map(|part| match part {
+                    Part::Kid => 1,
+                    Part::Nest(parts) => count(parts),
+                    Part::Token(_) | Part::Line => 0,
+                })
+                .sum()
+        }
+
+        count(&self.parts)
+    }
+
+    /// Whether the template ends with a kid, which takes in any infix
+    /// operators after the node.
+    pub fn is_open(&self) -> bool {
+        fn go(parts: &[Part]) -> Option<bool> {
+            parts.iter().rev().find_map(|part| match part {
+                Part::Token(_) => Some(false),
+                Part::Kid => Some(true),
+                Part::Line => None,
+                Part::Nest(parts) => go(parts),
+            })
+        }
+
+        go(&self.parts).unwrap_or(false)
+    }
+
+    /// The tokens of the template, in order.
+    fn tokens(&self) -> Vec<&str> {
+        fn go<'a>(parts: &'a [Part], tokens: &mut Vec<&'a str>) {
+            for part in parts {
+                match part {
+                    Part::Token(token) => tokens.push(token),
+                    Part::Nest(parts) => go(parts, tokens),
+                    Part::Kid | Part::Line => {}
+                }
+            }
+        }
+
+        let mut tokens = vec![];
+        go(&self.parts, &mut tokens);
+        tokens
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Grammar {
+    #[serde(default)]
+    pub infix: Vec<Infix>,
+    #[serde(default)]
+    pub prefix: Vec<Prefix>,
+    #[serde(default)]
+    pub mixfix: Vec<Mixfix>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// An operand was expected, and a hole was put in its place.
+    ExpectedOperand,
+    /// The `(` at the position is never closed.
+    UnclosedParen,
+    /// A token of the template of a [`Mixfix`] constructor is missing, and was
+    /// skipped.
+    ExpectedToken(String),
+    /// A character that no token starts with, which was skipped.
+    UnexpectedChar(char),
+    /// There is more text after the expression, which was skipped.
+    TrailingInput,
+}
+
+/// Where parsing had to recover, and how.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub position: Position,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Position { line, column, .. } = self.position;
+        write!(f, "{line}:{column}: ")?;
+        match &self.kind {
+            ErrorKind::ExpectedOperand => write!(f, "expected an operand"),
+            ErrorKind::UnclosedParen => write!(f, "this `(` is never closed"),
+            ErrorKind::ExpectedToken(token) => write!(f, "expected `{token}`"),
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected `{c}`"),
+            ErrorKind::TrailingInput => write!(f, "unexpected text after the expression"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The tree that was parsed, with holes where operands were missing, and the
+/// errors that were recovered from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Parsed {
+    pub tree: Tree,
+    pub errors: Vec<Error>,
+}
+
+impl Grammar {
+    /// Parses `text` as a single expression.
+    pub fn parse(&self, text: &str) -> Parsed {
+        let mut errors = vec![];
+        let tokens = self.tokenize(text, &mut errors);
+        let mut parser = Parser {
+            grammar: self,
+            tokens,
+            next: 0,
+            errors,
+        };
+        let tree = parser.expr(0);
+        if *parser.peek() != Token::End {
+            parser.error(ErrorKind::TrailingInput, parser.position());
+        }
+        let mut errors = parser.errors;
+        errors.sort_by_key(|error| error.position.offset);
+        Parsed { tree, errors }
+    }
+
+    /// Splits `text` into tokens, skipping the characters that no token
+    /// starts with and adding an error to `errors` for each of them.
+    fn tokenize(&self, text: &str, errors: &mut Vec<Error>) -> Vec<(Token, Position)> {
+        let mut symbols: Vec<&str> = self
+            .infix
+            .iter()
+            .map(|infix| infix.symbol.as_str())
+            .chain(self.prefix.iter().map(|prefix| prefix.symbol.as_str()))
+            .chain(self.mixfix.iter().flat_map(Mixfix::tokens))
+            .collect();
+        // the longest symbol that matches wins, so that `**` isn't `*` `*`
+        symbols.sort_by_key(|symbol| std::cmp::Reverse(symbol.len()));
+
+        let mut tokens = vec![];
+        let mut position = Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        };
+        let advance = |position: &mut Position, string: &str| {
+            for c in string.chars() {
+                position.offset += c.len_utf8();
+                if c == '\n' {
+                    position.line += 1;
+                    position.column = 1;
+                } else {
+                    position.column += 1;
+                }
+            }
+        };
+        while let Some(c) = text[position.offset..].chars().next() {
+            let rest = &text[position.offset..];
+            let start = position;
+            let (token, len) = if c.is_whitespace() {
+                advance(&mut position, &rest[..c.len_utf8()]);
+                continue;
+            } else if is_atom_char(c) || c == '?' {
+                let len = rest[c.len_utf8()..]
+                    .find(|c| !is_atom_char(c))
+                    .map_or(rest.len(), |len| len + c.len_utf8());
+                let word = &rest[..len];
+                if symbols.contains(&word) {
+                    (Token::Symbol(word.to_owned()), len)
+                } else {
+                    (Token::Atom(word.to_owned()), len)
+                }
+            } else if let Some(symbol) = symbols
+                .iter()
+                .find(|symbol| !symbol.is_empty() && rest.starts_with(**symbol))
+            {
+                (Token::Symbol((*symbol).to_owned()), symbol.len())
+            } else {
+                let token = match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    ',' => Token::Comma,
+                    c => {
+                        errors.push(Error {
+                            kind: ErrorKind::UnexpectedChar(c),
+                            position,
+                        });
+                        advance(&mut position, &rest[..c.len_utf8()]);
+                        continue;
+                    }
+                };
+                (token, c.len_utf8())
+            };
+            advance(&mut position, &rest[..len]);
+            tokens.push((token, start));
+        }
+        tokens.push((Token::End, position));
+        tokens
+    }
+}
+
+/// Whether `c` can be part of an atom.
+fn is_atom_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.')
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Atom(String),
+    Symbol(String),
+    Open,
+    Close,
+    Comma,
+    End,
+}
+
+struct Parser<'a> {
+    grammar: &'a Grammar,
+    tokens: Vec<(Token, Position)>,
+    /// The position of the next token in `tokens`, which always ends with
+    /// [`Token::End`].
+    next: usize,
+    errors: Vec<Error>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn position(&self) -> Position {
+        self.tokens[self.next].1
+    }
+
+    fn bump(&mut self) {
+        if self.next + 1 < self.tokens.len() {
+            self.next += 1;
+        }
+    }
+
+    fn error(&mut self, kind: ErrorKind, position: Position) {
+        self.errors.push(Error { kind, position });
+    }
+
+    /// Parses an expression whose infix operators have at least
+    /// `min_precedence`.
+    fn expr(&mut self, min_precedence: u32) -> Tree {
+        let mut lhs = self.operand();
+        loop {
+            let grammar = self.grammar;
+            let Token::Symbol(symbol) = self.peek() else {
+                break;
+            };
+            let Some(infix) = grammar
+                .infix
+                .iter()
+                .find(|infix| infix.symbol == *symbol && infix.precedence >= min_precedence)
+            else {
+                break;
+            };
+            self.bump();
+            let next_precedence = match infix.assoc {
+                Assoc::Left => infix.precedence + 1,
+                Assoc::Right => infix.precedence,
+            };
+            let rhs = self.expr(next_precedence);
+            lhs = Tree::new(infix.label.clone(), vec![lhs, rhs]);
+        }
+        lhs
+    }
+
+    fn operand(&mut self) -> Tree {
+        let position = self.position();
+        match self.peek().clone() {
+            Token::Atom(atom) => {
+                self.bump();
+                if *self.peek() == Token::Open {
+                    let kids = self.arguments();
+                    Tree::new(atom, kids)
+                } else {
+                    Tree::new(atom, vec![])
+                }
+            }
+            Token::Symbol(symbol) => {
+                let grammar = self.grammar;
+                let first = Part::Token(symbol.clone());
+                if let Some(mixfix) = grammar
+                    .mixfix
+                    .iter()
+                    .find(|mixfix| mixfix.parts.first() == Some(&first))
+                {
+                    let mut kids = vec![];
+                    self.parts(&mixfix.parts, &mut kids);
+                    return Tree::new(mixfix.label.clone(), kids);
+                }
+                let Some(prefix) = grammar.prefix.iter().find(|prefix| prefix.symbol == symbol)
+                else {
+                    // an infix operator without a left operand
+                    self.error(ErrorKind::ExpectedOperand, position);
+                    return Tree::hole();
+                };
+                self.bump();
+                let operand = self.expr(prefix.precedence);
+                Tree::new(prefix.label.clone(), vec![operand])
+            }
+            Token::Open => {
+                self.bump();
+                let tree = self.expr(0);
+                self.close(position);
+                tree
+            }
+            Token::Close | Token::Comma | Token::End => {
+                self.error(ErrorKind::ExpectedOperand, position);
+                Tree::hole()
+            }
+        }
+    }
+
+    /// Parses the kids of a [`Mixfix`] constructor from its template, skipping
+    /// the tokens that are missing.
+    fn parts(&mut self, parts: &[Part], kids: &mut Vec<Tree>) {
+        for part in parts {
+            match part {
+                Part::Token(token) => {
+                    if matches!(self.peek(), Token::Symbol(symbol) if symbol == token) {
+                        self.bump();
+                    } else {
+                        self.error(ErrorKind::ExpectedToken(token.clone()), self.position());
+                    }
+                }
+                Part::Kid => kids.push(self.expr(0)),
+                Part::Line => {}
+                Part::Nest(parts) => self.parts(parts, kids),
+            }
+        }
+    }
+
+    /// Parses the arguments of an application, from its `(`.
+    fn arguments(&mut self) -> Vec<Tree> {
+        let open = self.position();
+        self.bump();
+        let mut kids = vec![];
+        if *self.peek() == Token::Close {
+            self.bump();
+            return kids;
+        }
+        loop {
+            kids.push(self.expr(0));
+            if *self.peek() == Token::Comma {
+                self.bump();
+            } else {
+                self.close(open);
+                return kids;
+            }
+        }
+    }
+
+    /// Expects the `)` that closes the `(` at `open`.
+    fn close(&mut self, open: Position) {
+        if *self.peek() == Token::Close {
+            self.bump();
+        } else {
+            self.error(ErrorKind::UnclosedParen, open);
+        }
+    }
+}
+
+/// A grammar for arithmetic, to go with [`crate::schema::example`].
+pub fn example() -> Grammar {
+    Grammar {
+        infix: vec![
+            Infix::mk("+", "+", 1, Assoc::Left),
+            Infix::mk("*", "*", 2, Assoc::Left),
+        ],
+        prefix: vec![Prefix::mk("-", "neg", 3)],
+        mixfix: vec![
+            Mixfix::mk(
+                "let",
+                vec![
+                    Part::Token("let".to_owned()),
+                    Part::Kid,
+                    Part::Token("=".to_owned()),
+                    Part::Nest(vec![Part::Line, Part::Kid]),
+                ],
+            ),
+            Mixfix::mk("print", vec![Part::Token("print".to_owned()), Part::Kid]),
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::sexpr;
+
+    fn parse(grammar: &Grammar, text: &str) -> (String, Vec<ErrorKind>) {
+        let Parsed { tree, errors } = grammar.parse(text);
+        let kinds = errors.into_iter().map(|error| error.kind).collect();
+        (sexpr::to_string(&tree), kinds)
+    }
+
+    #[test]
+    fn grammar_parse_test1() {
+        let grammar = example();
+
+        assert_eq!(parse(&grammar, "1 + 2 * x").0, "(+ 1 (* 2 x))");
+        assert_eq!(parse(&grammar, "(1 + 2) * x").0, "(* (+ 1 2) x)");
+        assert_eq!(parse(&grammar, "1 + 2 + 3").0, "(+ (+ 1 2) 3)");
+        assert_eq!(
+            parse(&grammar, "-x * -(y + ?Expr)").0,
+            "(* (neg x) (neg (+ y ?Expr)))"
+        );
+        assert_eq!(
+            parse(&grammar, "program(let x = 1, print x + 1)").0,
+            "(program (let x 1) (print (+ x 1)))"
+        );
+        assert_eq!(parse(&grammar, "f()"), ("f".to_owned(), vec![]));
+    }
+
+    #[test]
+    fn grammar_parse_test2() {
+        let grammar = Grammar {
+            infix: vec![
+                Infix::mk("^", "pow", 3, Assoc::Right),
+                Infix::mk("**", "pow", 3, Assoc::Right),
+                Infix::mk("and", "and", 1, Assoc::Left),
+            ],
+            prefix: vec![Prefix::mk("not", "not", 2)],
+            mixfix: vec![],
+        };
+
+        assert_eq!(parse(&grammar, "a ^ b ** c").0, "(pow a (pow b c))");
+        assert_eq!(parse(&grammar, "not a and b").0, "(and (not a) b)");
+    }
+
+    #[test]
+    fn grammar_parse_test3() {
+        let grammar = example();
+
+        assert_eq!(
+            parse(&grammar, "1 + * 2"),
+            ("(+ 1 (* ? 2))".to_owned(), vec![ErrorKind::ExpectedOperand])
+        );
+        assert_eq!(
+            parse(&grammar, "f(1, , 2"),
+            (
+                "(f 1 ? 2)".to_owned(),
+                vec![ErrorKind::UnclosedParen, ErrorKind::ExpectedOperand]
+            )
+        );
+        assert_eq!(
+            parse(&grammar, "1 # + 2) 3"),
+            (
+                "(+ 1 2)".to_owned(),
+                vec![ErrorKind::UnexpectedChar('#'), ErrorKind::TrailingInput]
+            )
+        );
+        assert_eq!(
+            parse(&grammar, ""),
+            ("?".to_owned(), vec![ErrorKind::ExpectedOperand])
+        );
+
+        assert_eq!(
+            parse(&grammar, "let x 1"),
+            (
+                "(let x 1)".to_owned(),
+                vec![ErrorKind::ExpectedToken("=".to_owned())]
+            )
+        );
+
+        let error = &grammar.parse("1 +\n  * 2").errors[0];
+        assert_eq!(error.to_string(), "2:3: expected an operand");
+    }
+}
@@ -0,0 +1,362 @@
+//! Printing a tree back to the text that the grammar parses.
+//!
+//! Nodes of the operators are printed with them, in parentheses only where
+//! the precedence of the operators needs them, nodes of the mixfix
+//! constructors with their templates, and any other nodes as applications
+//! `f(a, b)` or atoms. Trees that the grammar can't express,
+//! such as labels that aren't atoms, are printed as well as they can be, but
+//! don't parse back to the same tree. Neither do folded nodes, whose kids are
+//! printed as `…`.
+//!
+//! The documents of the nodes can be kept in a [`DocCache`] between prints,
+//! so that after an edit only the subtree that it changed and the ancestors
+//! of that subtree are turned into documents again.
+
+use super::{Assoc, Grammar, Part, is_atom_char};
+use crate::pretty::{self, Doc, Layout};
+use crate::tree::{Index, Tree};
+use std::sync::Arc;
+
+/// How far the lines of operands and arguments that don't fit are indented.
+const INDENT: usize = 2;
+
+/// The documents of the nodes of a tree, kept between prints. It mirrors the
+/// tree, and the document of a node is made again only if it was forgotten.
+#[derive(Debug, Default)]
+pub struct DocCache {
+    /// The document of the node, and the precedence that it was made for.
+    doc: Option<(u32, Arc<Doc>)>,
+    kids: Vec<DocCache>,
+}
+
+impl DocCache {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Forgets the documents of the subtree at `index` and of its ancestors.
+    pub fn invalidate(&mut self, index: &Index) {
+        let mut cache = self;
+        for step in index {
+            cache.doc = None;
+            let Some(kid) = cache.kids.get_mut(*step) else {
+                return;
+            };
+            cache = kid;
+        }
+        cache.clear();
+    }
+}
+
+impl Grammar {
+    /// Prints `tree`, breaking lines to fit into `width` columns where
+    /// possible.
+    pub fn print(&self, tree: &Tree, width: usize) -> Layout {
+        self.print_cached(tree, width, &mut DocCache::default())
+    }
+
+    /// Prints `tree` like [`Self::print`], with the documents of the nodes
+    /// that are still in `cache`.
+    pub fn print_cached(&self, tree: &Tree, width: usize, cache: &mut DocCache) -> Layout {
+        pretty::render(&self.doc(tree, &mut Index::default(), 0, cache), width)
+    }
+
+    /// The document of `tree`, with the text of each node marked with its
+    /// index.
+    pub fn to_doc(&self, tree: &Tree) -> Doc {
+        self.doc(tree, &mut Index::default(), 0, &mut DocCache::default())
+    }
+
+    /// The document of `tree` at `index`, where operators must have at least
+    /// `min_precedence` to go without parentheses.
+    fn doc(
+        &self,
+        tree: &Tree,
+        index: &mut Index,
+        min_precedence: u32,
+        cache: &mut DocCache,
+    ) -> Doc {
+        if let Some((precedence, doc)) = &cache.doc {
+            if *precedence == min_precedence {
+                return Doc::Node(index.clone(), Arc::clone(doc));
+            }
+        }
+        if cache.kids.len() != tree.kids.len() {
+            cache.kids.clear();
+            cache.kids.resize_with(tree.kids.len(), DocCache::default);
+        }
+        let kids = &mut cache.kids;
+        let infix = self.infix.iter().find(|infix| infix.label == tree.label);
+        let prefix = self.prefix.iter().find(|prefix| prefix.label == tree.label);
+        let mixfix = self
+            .mixfix
+            .iter()
+            .find(|mixfix| mixfix.label == tree.label && mixfix.kid_count() == tree.kids.len());
+        let label = |text: &str| Doc::label(index.clone(), text);
+        let (doc, precedence) = match (tree.kids.as_slice(), infix, prefix, mixfix) {
+            (.., Some(mixfix)) if !tree.is_folded() => {
+                let progress = &mut Progress::default();
+                let doc = Doc::group(self.template_doc(&mixfix.parts, tree, index, kids, progress));
+                (doc, mixfix.is_open().then_some(0))
+            }
+            ([], ..) => (label(&tree.label), None),
+            _ if tree.is_folded() => {
+                let doc = Doc::Concat(vec![label(&tree.label), Doc::text("(…)")]);
+                (doc, None)
+            }
+            ([lhs, rhs], Some(infix), ..) => {
+                let (lhs_precedence, rhs_precedence) = match infix.assoc {
+                    Assoc::Left => (infix.precedence, infix.precedence + 1),
+                    Assoc::Right => (infix.precedence + 1, infix.precedence),
+                };
+                let symbol = label(&infix.symbol);
+                let lhs = self.kid_doc(lhs, index, 0, lhs_precedence, kids);
+                let rhs = self.kid_doc(rhs, index, 1, rhs_precedence, kids);
+                let doc = Doc::group(Doc::Concat(vec![
+                    lhs,
+                    Doc::text(" "),
+                    symbol,
+                    Doc::nest(INDENT, Doc::Concat(vec![Doc::line(), rhs])),
+                ]));
+                (doc, Some(infix.precedence))
+            }
+            ([operand], _, Some(prefix), _) => {
+                let mut docs = vec![label(&prefix.symbol)];
+                // `not x` must not run together into the atom `notx`
+                if prefix.symbol.ends_with(is_atom_char) {
+                    docs.push(Doc::text(" "));
+                }
+                docs.push(self.kid_doc(operand, index, 0, prefix.precedence, kids));
+                let doc = Doc::Concat(docs);
+                (doc, Some(prefix.precedence))
+            }
+            (trees, ..) => {
+                let name = label(&tree.label);
+                let mut args = vec![Doc::softline()];
+                for (step, kid) in trees.iter().enumerate() {
+                    if step > 0 {
+                        args.push(Doc::text(","));
+                        args.push(Doc::line());
+                    }
+                    args.push(self.kid_doc(kid, index, step, 0, kids));
+                }
+                let doc = Doc::group(Doc::Concat(vec![
+                    name,
+                    Doc::text("("),
+                    Doc::nest(INDENT, Doc::Concat(args)),
+                    Doc::softline(),
+                    Doc::text(")"),
+                ]));
+                (doc, None)
+            }
+        };
+        let doc = if precedence.is_some_and(|precedence| precedence < min_precedence) {
+            Doc::Concat(vec![Doc::text("("), doc, Doc::text(")")])
+        } else {
+            doc
+        };
+        let doc = Arc::new(doc);
+        cache.doc = Some((min_precedence, Arc::clone(&doc)));
+        Doc::Node(index.clone(), doc)
+    }
+
+    /// The document of `parts` of the template of the node of `tree`, with
+    /// the first token marked as its label.
+    fn template_doc(
+        &self,
+        parts: &[Part],
+        tree: &Tree,
+        index: &mut Index,
+        kids: &mut [DocCache],
+        progress: &mut Progress,
+    ) -> Doc {
+        let mut docs = vec![];
+        for part in parts {
+            if progress.space && matches!(part, Part::Token(_) | Part::Kid) {
+                docs.push(Doc::text(" "));
+            }
+            match part {
+                Part::Token(token) if !progress.labelled => {
+                    docs.push(Doc::label(index.clone(), token));
+                    progress.labelled = true;
+                    progress.space = true;
+                }
+                Part::Token(token) => {
+                    docs.push(Doc::text(token));
+                    progress.space = true;
+                }
+                Part::Kid => {
+                    let step = progress.kids;
+                    docs.push(self.kid_doc(&tree.kids[step], index, step, 0, kids));
+                    progress.kids += 1;
+                    progress.space = true;
+                }
+                Part::Line => {
+                    docs.push(Doc::line());
+                    progress.space = false;
+                }
+                Part::Nest(parts) => {
+                    let doc = self.template_doc(parts, tree, index, kids, progress);
+                    docs.push(Doc::nest(INDENT, doc));
+                }
+            }
+        }
+        Doc::Concat(docs)
+    }
+
+    /// The document of the kid of the node at `index` at `step`, kept in
+    /// `kids`, the caches of the kids of the node.
+    fn kid_doc(
+        &self,
+        kid: &Tree,
+        index: &mut Index,
+        step: usize,
+        min_precedence: u32,
+        kids: &mut [DocCache],
+    ) -> Doc {
+        index.push(step);
+        let doc = self.doc(kid, index, min_precedence, &mut kids[step]);
+        index.pop();
+        doc
+    }
+}
+
+/// How far the printing of a template got.
+#[derive(Default)]
+struct Progress {
+    /// The number of kids printed.
+    kids: usize,
+    /// Whether the next token or kid needs a space before it.
+    space: bool,
+    /// Whether the first token was printed, as the label of the node.
+    labelled: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DocCache;
+    use crate::grammar::{Assoc, Grammar, Infix, Prefix, example};
+    use crate::tree::{Index, sexpr};
+
+    fn print(grammar: &Grammar, text: &str, width: usize) -> String {
+        grammar.print(&sexpr::parse(text).unwrap(), width).text
+    }
+
+    #[test]
+    fn grammar_print_test1() {
+        let grammar = example();
+
+        assert_eq!(print(&grammar, "(+ 1 (* 2 x))", 80), "1 + 2 * x");
+        assert_eq!(print(&grammar, "(* (+ 1 2) x)", 80), "(1 + 2) * x");
+        assert_eq!(print(&grammar, "(+ 1 (+ 2 3))", 80), "1 + (2 + 3)");
+        assert_eq!(print(&grammar, "(neg (neg (+ x ?)))", 80), "--(x + ?)");
+        assert_eq!(
+            print(&grammar, "(program (let x 1) (print (+ x 1)))", 80),
+            "program(let x = 1, print x + 1)"
+        );
+        assert_eq!(
+            print(&grammar, "(program (let x (+ 1 2)) (print (+ x 1)))", 20),
+            "program(\n  let x = 1 + 2,\n  print x + 1\n)"
+        );
+        assert_eq!(
+            print(&grammar, "(+ abc (* def ghi))", 8),
+            "abc +\n  def *\n    ghi"
+        );
+    }
+
+    #[test]
+    fn grammar_print_test2() {
+        let grammar = Grammar {
+            infix: vec![
+                Infix::mk("^", "pow", 3, Assoc::Right),
+                Infix::mk("and", "and", 1, Assoc::Left),
+            ],
+            prefix: vec![Prefix::mk("not", "not", 2)],
+            mixfix: vec![],
+        };
+
+        assert_eq!(print(&grammar, "(pow a (pow b c))", 80), "a ^ b ^ c");
+        assert_eq!(print(&grammar, "(pow (pow a b) c)", 80), "(a ^ b) ^ c");
+        assert_eq!(print(&grammar, "(and (not a) b)", 80), "not a and b");
+        assert_eq!(print(&grammar, "(pow (not a) b)", 80), "(not a) ^ b");
+    }
+
+    #[test]
+    fn grammar_print_mixfix_test1() {
+        let grammar = example();
+
+        assert_eq!(
+            print(&grammar, "(let x (+ abc def))", 12),
+            "let x =\n  abc + def"
+        );
+        // the kid at the end would take in the operator otherwise
+        assert_eq!(print(&grammar, "(+ (print x) 1)", 80), "(print x) + 1");
+        assert_eq!(print(&grammar, "(+ 1 (let x 2))", 80), "1 + (let x = 2)");
+        // with another number of kids than the template, it is an application
+        assert_eq!(print(&grammar, "(let x)", 80), "let(x)");
+
+        let layout = grammar.print(&sexpr::parse("(let x 1)").unwrap(), 80);
+        assert_eq!(layout.label_of(&Index::default()), Some(0..3));
+        assert_eq!(layout.span_of(&Index(vec![1])), Some(8..9));
+    }
+
+    #[test]
+    fn grammar_print_round_trip_test1() {
+        let grammar = example();
+        let texts = [
+            "(program (let x (neg (* (+ 1 2) (neg y)))) (print (+ (+ a b) (+ c d))))",
+            "(f (g) (+ ?Expr (h 1 2 3)))",
+            "(* (let x (let y 1)) (neg (print (+ 1 2))))",
+        ];
+        for text in texts {
+            let tree = sexpr::parse(text).unwrap();
+            for width in [0, 10, 80] {
+                let printed = grammar.print(&tree, width).text;
+                let parsed = grammar.parse(&printed);
+                assert_eq!(parsed.errors, vec![], "{printed}");
+                assert_eq!(parsed.tree, tree, "{printed}");
+            }
+        }
+    }
+
+    #[test]
+    fn grammar_print_spans_test1() {
+        let tree = sexpr::parse("(* (+ 1 2) x)").unwrap();
+        let layout = example().print(&tree, 80);
+
+        assert_eq!(layout.text, "(1 + 2) * x");
+        assert_eq!(layout.span_of(&Index(vec![0])), Some(0..7));
+        assert_eq!(layout.span_of(&Index(vec![0, 1])), Some(5..6));
+        assert_eq!(layout.index_at(3), Some(&Index(vec![0])));
+        assert_eq!(layout.index_at(10), Some(&Index(vec![1])));
+        assert_eq!(layout.label_of(&Index::default()), Some(8..9));
+        assert_eq!(layout.label_of(&Index(vec![0])), Some(3..4));
+    }
+
+    #[test]
+    fn grammar_print_cached_test1() {
+        let grammar = example();
+        let mut tree = sexpr::parse("(program (let x (+ 1 2)) (print x))").unwrap();
+        let mut cache = DocCache::default();
+        grammar.print_cached(&tree, 80, &mut cache);
+
+        // a stale cache keeps the old documents, until the edited subtree is
+        // invalidated
+        tree.kids[0].kids[1].label = "*".to_owned();
+        let stale = grammar.print_cached(&tree, 80, &mut cache);
+        assert_eq!(stale.text, "program(let x = 1 + 2, print x)");
+        cache.invalidate(&Index(vec![0, 1]));
+        assert!(cache.kids[1].doc.is_some());
+        let layout = grammar.print_cached(&tree, 20, &mut cache);
+        assert_eq!(layout, grammar.print(&tree, 20));
+        assert_eq!(layout.text, "program(\n  let x = 1 * 2,\n  print x\n)");
+    }
+
+    #[test]
+    fn grammar_print_folded_test1() {
+        let mut tree = sexpr::parse("(print (+ 1 (f x)))").unwrap();
+        tree.at_index_mut(&Index(vec![0, 1])).unwrap().folded = true;
+
+        assert_eq!(example().print(&tree, 80).text, "print 1 + f(…)");
+    }
+}
@@ -0,0 +1,572 @@
+//! Undo/redo history of the edits made to a [`Tree`].
+
+use crate::tree::{Error, Folds, Index, Path, Step, Tree};
+use std::collections::VecDeque;
+
+/// An edit to a tree, as a value, so that it can be recorded and undone.
+///
+/// Each variant corresponds to one of the edit operations on [`Tree`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit<L = String> {
+    WrapPath {
+        index: Index,
+        path: Path<L>,
+    },
+    UnwrapPath {
+        outer: Index,
+        inner: Index,
+    },
+    InsertKid {
+        parent: Index,
+        position: Step,
+        kid: Tree<L>,
+    },
+    Delete {
+        index: Index,
+    },
+    Replace {
+        index: Index,
+        tree: Tree<L>,
+    },
+    ReplaceLabel {
+        index: Index,
+        label: L,
+    },
+    /// Sets an attribute, or removes it if `value` is `None`.
+    SetAttr {
+        index: Index,
+        key: String,
+        value: Option<String>,
+    },
+    SwapSiblings {
+        index: Index,
+        other: Step,
+    },
+    Move {
+        from: Index,
+        parent: Index,
+        position: Step,
+    },
+    Duplicate {
+        index: Index,
+    },
+    /// Folds or unfolds nodes.
+    SetFolds {
+        folds: Folds,
+    },
+}
+
+impl<L> Edit<L> {
+    /// The index of the smallest subtree that contains everything that the
+    /// edit changes. The nodes outside of it are left as they are, at the same
+    /// indices. An edit and its inverse have the same scope.
+    pub fn scope(&self) -> Index {
+        fn parent(index: &Index) -> Index {
+            index
+                .split_last()
+                .map_or_else(Index::default, |(parent, _)| parent)
+        }
+
+        fn common_prefix(a: &Index, b: &Index) -> Index {
+            Index(
+                a.iter()
+                    .zip(b)
+                    .take_while(|(a, b)| a == b)
+                    .map(|(step, _)| *step)
+                    .collect(),
+            )
+        }
+
+        match self {
+            Self::WrapPath { index, .. }
+            | Self::Replace { index, .. }
+            | Self::ReplaceLabel { index, .. }
+            | Self::SetAttr { index, .. } => index.clone(),
+            Self::UnwrapPath { outer, .. } => outer.clone(),
+            Self::InsertKid { parent, .. } => parent.clone(),
+            Self::Delete { index }
+            | Self::SwapSiblings { index, .. }
+            | Self::Duplicate { index } => parent(index),
+            Self::Move {
+                from, parent: to, ..
+            } => common_prefix(&parent(from), to),
+            Self::SetFolds { folds } => {
+                let mut indices = folds.iter().map(|(index, _)| index);
+                let first = indices.next().cloned().unwrap_or_default();
+                indices.fold(first, |scope, index| common_prefix(&scope, index))
+            }
+        }
+    }
+
+    /// Applies the edit to `tree`, fixing up `focus`, and returns the edit
+    /// that undoes it.
+    pub fn apply(self, tree: &mut Tree<L>, focus: &mut Index) -> Result<Self, Error>
+    where
+        L: Clone + Default,
+    {
+        match self {
+            Self::WrapPath { index, path } => {
+                tree.at_index(&index)?;
+                let mut inner = index.clone();
+                inner
+                    .0
+                    .extend(path.iter().map(|tooth| tooth.kids_left.len()));
+                // The focus stays on the same node, except that a focus on the
+                // wrapped subtree itself ends up on the outermost new node.
+                if index.is_prefix_of(focus) && index.len() < focus.len() {
+                    let rest = focus.0.split_off(index.len());
+                    *focus = inner.clone();
+                    focus.0.extend(rest);
+                }
+                tree.wrap_with_path_at_index(&index, path);
+                Ok(Self::UnwrapPath {
+                    outer: index,
+                    inner,
+                })
+            }
+            Self::UnwrapPath { outer, inner } => {
+                let path = tree.unwrap_path_at_index(&outer, &inner)?;
+                if inner.is_prefix_of(focus) {
+                    let rest = focus.0.split_off(inner.len());
+                    *focus = outer.clone();
+                    focus.0.extend(rest);
+                } else if outer.is_prefix_of(focus) {
+                    *focus = outer.clone();
+                }
+                Ok(Self::WrapPath { index: outer, path })
+            }
+            Self::InsertKid {
+                parent,
+                position,
+                kid,
+            } => {
+                let index = tree.insert_kid_at_index(&parent, position, kid, focus)?;
+                Ok(Self::Delete { index })
+            }
+            Self::Delete { index } => {
+                let kid = tree.delete_at_index(&index, focus)?;
+                let (parent, position) = index.split_last().ok_or(Error::AtRoot)?;
+                Ok(Self::InsertKid {
+                    parent,
+                    position,
+                    kid,
+                })
+            }
+            Self::Replace { index, tree: new } => {
+                let old = tree.replace_at_index(&index, new, focus)?;
+                Ok(Self::Replace { index, tree: old })
+            }
+            Self::ReplaceLabel { index, label } => {
+                let old = tree.replace_label_at_index(&index, label)?;
+                Ok(Self::ReplaceLabel { index, label: old })
+            }
+            Self::SetAttr { index, key, value } => {
+                let old = tree.set_attr_at_index(&index, key.clone(), value)?;
+                Ok(Self::SetAttr {
+                    index,
+                    key,
+                    value: old,
+                })
+            }
+            Self::SwapSiblings { index, other } => {
+                tree.swap_siblings_at_index(&index, other, focus)?;
+                Ok(Self::SwapSiblings { index, other })
+            }
+            Self::Move {
+                from,
+                parent,
+                position,
+            } => {
+                let index = tree.move_at_index(&from, &parent, position, focus)?;
+                // Where the subtree came from, in the coordinates of the tree
+                // before the inverse move.
+                let mut back = from;
+                back.shift_for_insert(&index);
+                let (parent, position) = back.split_last().ok_or(Error::AtRoot)?;
+                Ok(Self::Move {
+                    from: index,
+                    parent,
+                    position,
+                })
+            }
+            Self::Duplicate { index } => {
+                let index = tree.duplicate_at_index(&index, focus)?;
+                Ok(Self::Delete { index })
+            }
+            Self::SetFolds { folds } => {
+                let old = tree.set_folds(folds)?;
+                Ok(Self::SetFolds { folds: old })
+            }
+        }
+    }
+}
+
+/// An edit that was applied, together with what is needed to undo and redo
+/// it.
+#[derive(Debug, Clone)]
+struct Entry<L> {
+    edit: Edit<L>,
+    inverse: Edit<L>,
+    focus_before: Index,
+    focus_after: Index,
+}
+
+/// A bounded undo/redo stack of [`Edit`]s.
+#[derive(Debug, Clone)]
+pub struct History<L = String> {
+    undo: VecDeque<Entry<L>>,
+    redo: Vec<Entry<L>>,
+    capacity: usize,
+}
+
+impl<L> Default for History<L> {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl<L> History<L> {
+    /// A history that remembers at most `capacity` edits.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            capacity,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The edit that [`Self::undo`] would undo, if any.
+    pub fn next_undo(&self) -> Option<&Edit<L>> {
+        self.undo.back().map(|entry| &entry.edit)
+    }
+
+    /// The edit that [`Self::redo`] would redo, if any.
+    pub fn next_redo(&self) -> Option<&Edit<L>> {
+        self.redo.last().map(|entry| &entry.edit)
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Applies `edit` to `tree` and records it. This discards the redo stack.
+    pub fn apply(
+        &mut self,
+        edit: Edit<L>,
+        tree: &mut Tree<L>,
+        focus: &mut Index,
+    ) -> Result<(), Error>
+    where
+        L: Clone + Default,
+    {
+        let focus_before = focus.clone();
+        let inverse = edit.clone().apply(tree, focus)?;
+        self.redo.clear();
+        self.push_undo(Entry {
+            edit,
+            inverse,
+            focus_before,
+            focus_after: focus.clone(),
+        });
+        Ok(())
+    }
+
+    /// Undoes the last edit. Returns whether there was an edit to undo. If
+    /// it can't be undone, it is kept, to be undone later.
+    pub fn undo(&mut self, tree: &mut Tree<L>, focus: &mut Index) -> Result<bool, Error>
+    where
+        L: Clone + Default,
+    {
+        let Some(entry) = self.undo.pop_back() else {
+            return Ok(false);
+        };
+        let edit = match entry.inverse.clone().apply(tree, focus) {
+            Ok(edit) => edit,
+            Err(err) => {
+                self.undo.push_back(entry);
+                return Err(err);
+            }
+        };
+        focus.clone_from(&entry.focus_before);
+        self.redo.push(Entry { edit, ..entry });
+        Ok(true)
+    }
+
+    /// Redoes the last undone edit. Returns whether there was an edit to redo.
+    /// If it can't be redone, it is kept, to be redone later.
+    pub fn redo(&mut self, tree: &mut Tree<L>, focus: &mut Index) -> Result<bool, Error>
+    where
+        L: Clone + Default,
+    {
+        let Some(entry) = self.redo.pop() else {
+            return Ok(false);
+        };
+        let inverse = match entry.edit.clone().apply(tree, focus) {
+            Ok(inverse) => inverse,
+            Err(err) => {
+                self.redo.push(entry);
+                return Err(err);
+            }
+        };
+        focus.clone_from(&entry.focus_after);
+        self.push_undo(Entry { inverse, ..entry });
+        Ok(true)
+    }
+
+    /// Records `entry`, forgetting the oldest edit if there are too many. A
+    /// history without capacity records nothing.
+    fn push_undo(&mut self, entry: Entry<L>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.undo.len() == self.capacity {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Tooth;
+    use crate::tree::example;
+
+    /// Applies `edit`, then checks that undo and redo restore the trees and
+    /// focuses from before and after it.
+    fn check_round_trip(edit: Edit, focus: &Index) {
+        let mut history = History::default();
+        let mut tree = example();
+        let mut focus_new = focus.clone();
+
+        history.apply(edit, &mut tree, &mut focus_new).unwrap();
+        let tree_after = tree.clone();
+        let focus_after = focus_new.clone();
+
+        assert!(history.undo(&mut tree, &mut focus_new).unwrap());
+        assert_eq!(tree, example());
+        assert_eq!(&focus_new, focus);
+
+        assert!(history.redo(&mut tree, &mut focus_new).unwrap());
+        assert_eq!(tree, tree_after);
+        assert_eq!(focus_new, focus_after);
+
+        assert!(history.undo(&mut tree, &mut focus_new).unwrap());
+        assert_eq!(tree, example());
+    }
+
+    #[test]
+    fn history_round_trip_test1() {
+        let focus = Index(vec![0, 1]);
+        let edits = [
+            Edit::WrapPath {
+                index: Index(vec![0]),
+                path: vec![Tooth::mk("X", &[Tree::mk("L", &[])], &[])],
+            },
+            Edit::UnwrapPath {
+                outer: Index(vec![]),
+                inner: Index(vec![0]),
+            },
+            Edit::InsertKid {
+                parent: Index(vec![0]),
+                position: 1,
+                kid: Tree::mk("X", &[]),
+            },
+            Edit::Delete {
+                index: Index(vec![0, 0]),
+            },
+            Edit::Replace {
+                index: Index(vec![0]),
+                tree: Tree::mk("X", &[]),
+            },
+            Edit::ReplaceLabel {
+                index: Index(vec![1]),
+                label: "X".to_owned(),
+            },
+            Edit::SetAttr {
+                index: Index(vec![0, 1]),
+                key: "type".to_owned(),
+                value: Some("int".to_owned()),
+            },
+            Edit::SwapSiblings {
+                index: Index(vec![0]),
+                other: 1,
+            },
+            Edit::Move {
+                from: Index(vec![0, 0]),
+                parent: Index(vec![]),
+                position: 2,
+            },
+            Edit::Move {
+                from: Index(vec![1]),
+                parent: Index(vec![0]),
+                position: 0,
+            },
+            Edit::Duplicate {
+                index: Index(vec![0, 1]),
+            },
+            Edit::SetFolds {
+                folds: vec![(Index(vec![0]), true), (Index(vec![0, 1]), true)],
+            },
+        ];
+        for edit in edits {
+            check_round_trip(edit, &focus);
+        }
+    }
+
+    #[test]
+    fn edit_scope_test1() {
+        let move_edit = Edit::Move {
+            from: Index(vec![0, 1, 0]),
+            parent: Index(vec![0, 2]),
+            position: 0,
+        };
+        let mut history = History::default();
+        let mut tree = Tree::mk(
+            "A",
+            &[Tree::mk(
+                "B",
+                &[
+                    Tree::mk("C", &[]),
+                    Tree::mk("D", &[Tree::mk("E", &[])]),
+                    Tree::mk("F", &[]),
+                ],
+            )],
+        );
+
+        assert_eq!(move_edit.scope(), Index(vec![0]));
+        assert_eq!(
+            Edit::<String>::Delete {
+                index: Index(vec![0, 1])
+            }
+            .scope(),
+            Index(vec![0])
+        );
+
+        history
+            .apply(move_edit, &mut tree, &mut Index::default())
+            .unwrap();
+        assert_eq!(history.next_undo().map(Edit::scope), Some(Index(vec![0])));
+        history.undo(&mut tree, &mut Index::default()).unwrap();
+        assert_eq!(history.next_undo(), None);
+        assert_eq!(history.next_redo().map(Edit::scope), Some(Index(vec![0])));
+    }
+
+    #[test]
+    fn history_capacity_test1() {
+        let mut history = History::new(2);
+        let mut tree = example();
+        let mut focus = Index::default();
+
+        for label in ["X", "Y", "Z"] {
+            let edit = Edit::ReplaceLabel {
+                index: Index(vec![]),
+                label: label.to_owned(),
+            };
+            history.apply(edit, &mut tree, &mut focus).unwrap();
+        }
+
+        assert!(history.undo(&mut tree, &mut focus).unwrap());
+        assert!(history.undo(&mut tree, &mut focus).unwrap());
+        assert!(!history.undo(&mut tree, &mut focus).unwrap());
+        assert_eq!(tree.label, "X");
+    }
+
+    #[test]
+    fn history_capacity_test2() {
+        let mut history = History::new(0);
+        let mut tree = example();
+        let mut focus = Index::default();
+
+        let edit = Edit::ReplaceLabel {
+            index: Index(vec![]),
+            label: "X".to_owned(),
+        };
+        history.apply(edit, &mut tree, &mut focus).unwrap();
+
+        assert_eq!(tree.label, "X");
+        assert!(!history.can_undo());
+        assert!(!history.undo(&mut tree, &mut focus).unwrap());
+    }
+
+    #[test]
+    fn history_failed_undo_test1() {
+        let mut history = History::default();
+        let mut tree = example();
+        let mut focus = Index::default();
+        let edit = Edit::InsertKid {
+            parent: Index(vec![]),
+            position: 0,
+            kid: Tree::mk("X", &[]),
+        };
+        history.apply(edit.clone(), &mut tree, &mut focus).unwrap();
+
+        // the inserted kid is gone, so there is nothing to delete, but the
+        // edit is kept to be undone later
+        let kids = std::mem::take(&mut tree.kids);
+        assert!(history.undo(&mut tree, &mut focus).is_err());
+        assert_eq!(history.next_undo(), Some(&edit));
+        tree.kids = kids;
+        assert!(history.undo(&mut tree, &mut focus).unwrap());
+        assert_eq!(tree, example());
+    }
+
+    #[test]
+    fn history_failed_redo_test1() {
+        let mut history = History::default();
+        let mut tree = example();
+        let mut focus = Index::default();
+        let edit = Edit::Delete {
+            index: Index(vec![0]),
+        };
+        history.apply(edit.clone(), &mut tree, &mut focus).unwrap();
+        let tree_after = tree.clone();
+        history.undo(&mut tree, &mut focus).unwrap();
+
+        // there is no kid to delete, but the edit is kept to be redone later
+        let kids = std::mem::take(&mut tree.kids);
+        assert!(history.redo(&mut tree, &mut focus).is_err());
+        assert_eq!(history.next_redo(), Some(&edit));
+        tree.kids = kids;
+        assert!(history.redo(&mut tree, &mut focus).unwrap());
+        assert_eq!(tree, tree_after);
+    }
+
+    #[test]
+    fn history_generic_label_test1() {
+        let mut history = History::default();
+        let mut tree = Tree::new(1, vec![Tree::new(2, vec![]), Tree::new(3, vec![])]);
+        let mut focus = Index(vec![1]);
+        let original = tree.clone();
+
+        let edit = Edit::WrapPath {
+            index: Index(vec![0]),
+            path: vec![Tooth::new(4, vec![], vec![Tree::new(5, vec![])])],
+        };
+        history.apply(edit, &mut tree, &mut focus).unwrap();
+        assert_eq!(tree.kids[0].label, 4);
+        history
+            .apply(
+                Edit::Duplicate {
+                    index: focus.clone(),
+                },
+                &mut tree,
+                &mut focus,
+            )
+            .unwrap();
+        assert_eq!(tree.kids.len(), 3);
+
+        while history.undo(&mut tree, &mut focus).unwrap() {}
+        assert_eq!(tree, original);
+        assert_eq!(focus, Index(vec![1]));
+    }
+}
@@ -2,4 +2,10 @@
 
 mod app;
 pub use app::App;
+pub mod document;
+pub mod grammar;
+pub mod history;
+pub mod pretty;
+pub mod schema;
+pub mod template;
 pub mod tree;
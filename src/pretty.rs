@@ -0,0 +1,321 @@
+//! A pretty-printer in the style of Wadler's "A prettier printer".
+//!
+//! A [`Doc`] is text with optional line breaks in groups, and [`render`] lays
+//! it out in a width, breaking the lines of only the groups that don't fit.
+//! Parts of a document can be marked with the node of a tree that they were
+//! printed from, so that the [`Layout`] knows which span of text each node
+//! took.
+
+use crate::tree::Index;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Doc {
+    Text(String),
+    /// A line break, or the text if the group that it is in fits on the line.
+    Break(&'static str),
+    /// A document whose line breaks are indented by this much more.
+    Nest(usize, Box<Doc>),
+    /// A document that is printed on one line if it fits, and with all of its
+    /// breaks as line breaks otherwise.
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+    /// A document printed from the node at the index. It is shared, so that
+    /// the documents of nodes that didn't change can be kept and used again.
+    Node(Index, Arc<Doc>),
+    /// The text of the label of the node at the index.
+    Label(Index, String),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    /// A line break, or a space on one line.
+    pub fn line() -> Self {
+        Self::Break(" ")
+    }
+
+    /// A line break, or nothing on one line.
+    pub fn softline() -> Self {
+        Self::Break("")
+    }
+
+    pub fn nest(indent: usize, doc: Self) -> Self {
+        Self::Nest(indent, Box::new(doc))
+    }
+
+    pub fn group(doc: Self) -> Self {
+        Self::Group(Box::new(doc))
+    }
+
+    pub fn node(index: Index, doc: Self) -> Self {
+        Self::Node(index, Arc::new(doc))
+    }
+
+    pub fn label(index: Index, text: impl Into<String>) -> Self {
+        Self::Label(index, text.into())
+    }
+}
+
+/// The span of text that a node or its label was printed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub index: Index,
+    /// The range in bytes.
+    pub range: Range<usize>,
+}
+
+/// A rendered document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    pub text: String,
+    /// The spans of the nodes, parents before their kids.
+    pub spans: Vec<Span>,
+    /// The spans of the labels, in the order of the text.
+    pub labels: Vec<Span>,
+    /// The positions in `spans` and `labels` of the spans of each node.
+    span_positions: HashMap<Index, usize>,
+    label_positions: HashMap<Index, usize>,
+}
+
+impl Layout {
+    /// The span of the node at `index`, if it was printed.
+    pub fn span_of(&self, index: &Index) -> Option<Range<usize>> {
+        let &position = self.span_positions.get(index)?;
+        Some(self.spans[position].range.clone())
+    }
+
+    /// The span of the label of the node at `index`, if it was printed.
+    pub fn label_of(&self, index: &Index) -> Option<Range<usize>> {
+        let &position = self.label_positions.get(index)?;
+        Some(self.labels[position].range.clone())
+    }
+
+    /// The innermost node whose span has the byte at `offset`.
+    pub fn index_at(&self, offset: usize) -> Option<&Index> {
+        self.index_spanning(offset..offset + 1)
+    }
+
+    /// The innermost node whose span has all of the bytes in `range`.
+    pub fn index_spanning(&self, range: Range<usize>) -> Option<&Index> {
+        // of the spans that contain each other, the innermost comes last
+        self.spans
+            .iter()
+            .rev()
+            .find(|span| span.range.start <= range.start && range.end <= span.range.end)
+            .map(|span| &span.index)
+    }
+}
+
+/// What is left to print, innermost first.
+enum Command<'a> {
+    Print {
+        indent: usize,
+        flat: bool,
+        doc: &'a Doc,
+    },
+    /// Ends the span at this position in [`Layout::spans`].
+    EndSpan(usize),
+}
+
+/// Lays out `doc` in `width` columns where possible.
+pub fn render(doc: &Doc, width: usize) -> Layout {
+    let mut layout = Layout::default();
+    let mut column = 0;
+    let mut stack = vec![Command::Print {
+        indent: 0,
+        flat: false,
+        doc,
+    }];
+    while let Some(command) = stack.pop() {
+        let (indent, flat, doc) = match command {
+            Command::Print { indent, flat, doc } => (indent, flat, doc),
+            Command::EndSpan(span) => {
+                layout.spans[span].range.end = layout.text.len();
+                continue;
+            }
+        };
+        match doc {
+            Doc::Text(text) => {
+                layout.text.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Label(index, text) => {
+                let start = layout.text.len();
+                layout.text.push_str(text);
+                column += text.chars().count();
+                layout
+                    .label_positions
+                    .entry(index.clone())
+                    .or_insert(layout.labels.len());
+                layout.labels.push(Span {
+                    index: index.clone(),
+                    range: start..layout.text.len(),
+                });
+            }
+            Doc::Break(text) if flat => {
+                layout.text.push_str(text);
+                column += text.chars().count();
+            }
+            Doc::Break(_) => {
+                layout.text.push('\n');
+                layout.text.extend(std::iter::repeat_n(' ', indent));
+                column = indent;
+            }
+            Doc::Nest(more, doc) => stack.push(Command::Print {
+                indent: indent + more,
+                flat,
+                doc,
+            }),
+            Doc::Group(doc) => {
+                let flat = flat || fits(doc, width.saturating_sub(column), &stack);
+                stack.push(Command::Print { indent, flat, doc });
+            }
+            Doc::Concat(docs) => {
+                stack.extend(
+                    docs.iter()
+                        .rev()
+                        .map(|doc| Command::Print { indent, flat, doc }),
+                );
+            }
+            Doc::Node(index, doc) => {
+                let start = layout.text.len();
+                stack.push(Command::EndSpan(layout.spans.len()));
+                layout
+                    .span_positions
+                    .entry(index.clone())
+                    .or_insert(layout.spans.len());
+                layout.spans.push(Span {
+                    index: index.clone(),
+                    range: start..start,
+                });
+                stack.push(Command::Print { indent, flat, doc });
+            }
+        }
+    }
+    layout
+}
+
+/// Whether `doc` fits into `width` columns on one line, together with what
+/// follows it on the `rest` of its line.
+fn fits(doc: &Doc, width: usize, rest: &[Command<'_>]) -> bool {
+    let mut width = width;
+    let mut docs = vec![(true, doc)];
+    let mut rest = rest.iter().rev();
+    loop {
+        let Some((flat, doc)) = docs.pop().or_else(|| {
+            rest.find_map(|command| match command {
+                Command::Print { flat, doc, .. } => Some((*flat, *doc)),
+                Command::EndSpan(_) => None,
+            })
+        }) else {
+            return true;
+        };
+        match doc {
+            Doc::Text(text) | Doc::Label(_, text) => {
+                let Some(left) = width.checked_sub(text.chars().count()) else {
+                    return false;
+                };
+                width = left;
+            }
+            Doc::Break(text) if flat => {
+                let Some(left) = width.checked_sub(text.chars().count()) else {
+                    return false;
+                };
+                width = left;
+            }
+            // the line ends here, so the rest doesn't matter
+            Doc::Break(_) => return true,
+            Doc::Nest(_, doc) | Doc::Group(doc) => docs.push((flat, doc)),
+            Doc::Node(_, doc) => docs.push((flat, doc)),
+            Doc::Concat(kids) => docs.extend(kids.iter().rev().map(|doc| (flat, doc))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `f(a, b, …)`, with the arguments on lines of their own if they don't
+    /// fit.
+    fn call(name: &str, args: Vec<Doc>) -> Doc {
+        let mut inner = vec![Doc::softline()];
+        for (i, arg) in args.into_iter().enumerate() {
+            if i > 0 {
+                inner.push(Doc::text(","));
+                inner.push(Doc::line());
+            }
+            inner.push(arg);
+        }
+        Doc::group(Doc::Concat(vec![
+            Doc::text(name),
+            Doc::text("("),
+            Doc::nest(2, Doc::Concat(inner)),
+            Doc::softline(),
+            Doc::text(")"),
+        ]))
+    }
+
+    #[test]
+    fn pretty_render_test1() {
+        let doc = call(
+            "f",
+            vec![
+                Doc::text("a"),
+                call("g", vec![Doc::text("b"), Doc::text("c")]),
+            ],
+        );
+
+        assert_eq!(render(&doc, 80).text, "f(a, g(b, c))");
+        assert_eq!(render(&doc, 12).text, "f(\n  a,\n  g(b, c)\n)");
+        assert_eq!(
+            render(&doc, 4).text,
+            "f(\n  a,\n  g(\n    b,\n    c\n  )\n)"
+        );
+    }
+
+    #[test]
+    fn pretty_render_test2() {
+        // the `)` after the group has to fit on its line too
+        let doc = Doc::Concat(vec![
+            Doc::group(Doc::Concat(vec![
+                Doc::text("abc"),
+                Doc::line(),
+                Doc::text("d"),
+            ])),
+            Doc::text(")"),
+        ]);
+
+        assert_eq!(render(&doc, 6).text, "abc d)");
+        assert_eq!(render(&doc, 5).text, "abc\nd)");
+    }
+
+    #[test]
+    fn pretty_spans_test1() {
+        let doc = Doc::node(
+            Index::default(),
+            call(
+                "f",
+                vec![
+                    Doc::node(Index(vec![0]), Doc::text("a")),
+                    Doc::node(Index(vec![1]), Doc::text("bc")),
+                ],
+            ),
+        );
+        let layout = render(&doc, 80);
+
+        assert_eq!(layout.text, "f(a, bc)");
+        assert_eq!(layout.span_of(&Index::default()), Some(0..8));
+        assert_eq!(layout.span_of(&Index(vec![1])), Some(5..7));
+        assert_eq!(layout.index_at(6), Some(&Index(vec![1])));
+        assert_eq!(layout.index_at(4), Some(&Index::default()));
+        assert_eq!(layout.index_at(8), None);
+        assert_eq!(layout.index_spanning(5..7), Some(&Index(vec![1])));
+        assert_eq!(layout.index_spanning(2..6), Some(&Index::default()));
+    }
+}
@@ -0,0 +1,499 @@
+//! Schemas that say which trees are valid, for editing structured languages.
+//!
+//! A schema has sorts, such as expressions and statements, and constructors,
+//! which say that a node with a certain label is of a certain sort and which
+//! sorts its kids are. A sort may also allow leaves with any other label, for
+//! names and literals. Holes are valid anywhere, since they are yet to be
+//! filled in, except for typed holes of another sort than the one expected.
+
+use crate::grammar::{self, Grammar};
+use crate::tree::{Index, Tooth, Tree, sexpr};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Sort {
+    /// Capitalized, as in `Expr`, so that the label of a typed hole of the
+    /// sort is a hole; see [`crate::tree::is_sort_name`].
+    pub name: String,
+    /// Whether a leaf whose label isn't a constructor of any sort is of this
+    /// sort, such as a name or a number.
+    #[serde(default)]
+    pub atoms: bool,
+}
+
+impl Sort {
+    pub fn mk(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            atoms: false,
+        }
+    }
+
+    /// A sort that also has leaves with any label.
+    pub fn atoms(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            atoms: true,
+        }
+    }
+}
+
+/// The sorts of the kids of a constructor.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum Kids {
+    /// Exactly one kid of each sort, in order.
+    Fixed(Vec<String>),
+    /// Any number of kids of the sort, such as the statements of a block.
+    Many(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Constructor {
+    pub label: String,
+    pub sort: String,
+    pub kids: Kids,
+}
+
+impl Constructor {
+    pub fn mk(label: &str, sort: &str, kids: &[&str]) -> Self {
+        Self {
+            label: label.to_owned(),
+            sort: sort.to_owned(),
+            kids: Kids::Fixed(kids.iter().map(|&kid| kid.to_owned()).collect()),
+        }
+    }
+
+    /// A constructor with any number of kids of the sort `kid`.
+    pub fn many(label: &str, sort: &str, kid: &str) -> Self {
+        Self {
+            label: label.to_owned(),
+            sort: sort.to_owned(),
+            kids: Kids::Many(kid.to_owned()),
+        }
+    }
+
+    /// A node of the constructor, with a typed hole for each of its kids.
+    pub fn instantiate(&self) -> Tree {
+        let kids = match &self.kids {
+            Kids::Fixed(sorts) => sorts.iter().map(|sort| Tree::typed_hole(sort)).collect(),
+            Kids::Many(_) => vec![],
+        };
+        Tree::new(self.label.clone(), kids)
+    }
+
+    /// The context of the kid at `slot` of a node of the constructor, with
+    /// typed holes for the other kids, to wrap a tree with.
+    pub fn tooth(&self, slot: usize) -> Tooth {
+        let holes = |sorts: &[String]| -> Vec<Tree> {
+            sorts.iter().map(|sort| Tree::typed_hole(sort)).collect()
+        };
+        let (kids_left, kids_right) = match &self.kids {
+            Kids::Fixed(sorts) if slot < sorts.len() => {
+                (holes(&sorts[..slot]), holes(&sorts[slot + 1..]))
+            }
+            Kids::Fixed(_) | Kids::Many(_) => (vec![], vec![]),
+        };
+        Tooth::new(self.label.clone(), kids_left, kids_right)
+    }
+
+    /// The sort of the kid at `step`, if the constructor has such a kid.
+    pub fn kid_sort(&self, step: usize) -> Option<&str> {
+        match &self.kids {
+            Kids::Fixed(sorts) => sorts.get(step).map(String::as_str),
+            Kids::Many(sort) => Some(sort),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Schema {
+    /// The sort of the root.
+    pub root: String,
+    pub sorts: Vec<Sort>,
+    pub constructors: Vec<Constructor>,
+    /// How expressions of the language are typed in as text.
+    #[serde(default)]
+    pub grammar: Grammar,
+}
+
+impl Schema {
+    pub fn sort(&self, name: &str) -> Option<&Sort> {
+        self.sorts.iter().find(|sort| sort.name == name)
+    }
+
+    /// The constructor with `label` of `sort`, or else any other constructor
+    /// with `label`.
+    pub fn constructor(&self, label: &str, sort: &str) -> Option<&Constructor> {
+        self.constructors
+            .iter()
+            .find(|constructor| constructor.label == label && constructor.sort == sort)
+            .or_else(|| {
+                self.constructors
+                    .iter()
+                    .find(|constructor| constructor.label == label)
+            })
+    }
+
+    /// The constructors of `sort`.
+    pub fn constructors_of<'a>(&'a self, sort: &'a str) -> impl Iterator<Item = &'a Constructor> {
+        self.constructors
+            .iter()
+            .filter(move |constructor| constructor.sort == sort)
+    }
+
+    /// The sort that the node of `tree` at `index` is expected to be of, going
+    /// by the constructors of its ancestors, if they are known.
+    pub fn sort_at<'a>(&'a self, tree: &Tree, index: &Index) -> Option<&'a str> {
+        let mut sort = self.root.as_str();
+        let mut tree = tree;
+        for step in index {
+            let constructor = self.constructor(&tree.label, sort)?;
+            sort = constructor.kid_sort(*step)?;
+            tree = tree.kids.get(*step)?;
+        }
+        Some(sort)
+    }
+
+    /// The ways to put a constructor at `index` in `tree` so that it fits
+    /// there: filling it in, and, unless it is a hole, wrapping it as a kid of
+    /// the same sort.
+    pub fn completions(&self, tree: &Tree, index: &Index) -> Vec<Completion> {
+        let Ok(here) = tree.at_index(index) else {
+            return vec![];
+        };
+        let Some(sort) = here.hole_sort().or_else(|| self.sort_at(tree, index)) else {
+            return vec![];
+        };
+        let mut completions: Vec<_> = self
+            .constructors_of(sort)
+            .map(|constructor| Completion::Fill(constructor.clone()))
+            .collect();
+        if here.is_hole() {
+            return completions;
+        }
+        for constructor in self.constructors_of(sort) {
+            let slots = match &constructor.kids {
+                Kids::Fixed(sorts) => sorts.iter().map(String::as_str).enumerate().collect(),
+                Kids::Many(kid) => vec![(0, kid.as_str())],
+            };
+            for (slot, kid_sort) in slots {
+                if kid_sort == sort {
+                    completions.push(Completion::Wrap(constructor.clone(), slot));
+                }
+            }
+        }
+        completions
+    }
+
+    /// The nodes of `tree` that break the schema, in preorder.
+    pub fn validate(&self, tree: &Tree) -> Vec<Violation> {
+        let mut violations = vec![];
+        self.validate_as(tree, &self.root, &mut Index::default(), &mut violations);
+        violations
+    }
+
+    fn validate_as(
+        &self,
+        tree: &Tree,
+        sort: &str,
+        index: &mut Index,
+        violations: &mut Vec<Violation>,
+    ) {
+        let mut violate = |kind| {
+            violations.push(Violation {
+                index: index.clone(),
+                kind,
+            });
+        };
+        if tree.is_hole() {
+            if let Some(hole_sort) = tree.hole_sort().filter(|&hole_sort| hole_sort != sort) {
+                violate(ViolationKind::WrongSort {
+                    label: tree.label.clone(),
+                    expected: sort.to_owned(),
+                    found: hole_sort.to_owned(),
+                });
+            }
+            return;
+        }
+
+        let Some(constructor) = self.constructor(&tree.label, sort) else {
+            if !self.sort(sort).is_some_and(|sort| sort.atoms) {
+                violate(ViolationKind::UnknownLabel {
+                    label: tree.label.clone(),
+                    sort: sort.to_owned(),
+                });
+            } else if !tree.kids.is_empty() {
+                violate(ViolationKind::WrongArity {
+                    label: tree.label.clone(),
+                    expected: 0,
+                    found: tree.kids.len(),
+                });
+            }
+            return;
+        };
+        if constructor.sort != sort {
+            violate(ViolationKind::WrongSort {
+                label: tree.label.clone(),
+                expected: sort.to_owned(),
+                found: constructor.sort.clone(),
+            });
+        }
+        if let Kids::Fixed(sorts) = &constructor.kids {
+            if sorts.len() != tree.kids.len() {
+                violate(ViolationKind::WrongArity {
+                    label: tree.label.clone(),
+                    expected: sorts.len(),
+                    found: tree.kids.len(),
+                });
+            }
+        }
+
+        for (i, kid) in tree.kids.iter().enumerate() {
+            // the extra kids were reported along with the arity
+            let Some(kid_sort) = constructor.kid_sort(i) else {
+                break;
+            };
+            index.push(i);
+            self.validate_as(kid, kid_sort, index, violations);
+            index.pop();
+        }
+    }
+}
+
+/// A way to put a constructor at a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Completion {
+    /// Replaces the node with a node of the constructor.
+    Fill(Constructor),
+    /// Wraps the node with a node of the constructor, as its kid at the slot.
+    Wrap(Constructor, usize),
+}
+
+impl Completion {
+    pub fn constructor(&self) -> &Constructor {
+        match self {
+            Self::Fill(constructor) | Self::Wrap(constructor, _) => constructor,
+        }
+    }
+
+    /// The S-expression of what the completion puts at the node, with `●`
+    /// for the node that it wraps.
+    pub fn text(&self) -> String {
+        let tree = match self {
+            Self::Fill(constructor) => constructor.instantiate(),
+            Self::Wrap(constructor, slot) => {
+                Tree::mk("●", &[]).wrap_with_tooth(constructor.tooth(*slot))
+            }
+        };
+        sexpr::to_string(&tree)
+    }
+}
+
+/// A node that breaks a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub index: Index,
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The label is not a constructor, and `sort` has no atoms.
+    UnknownLabel { label: String, sort: String },
+    /// The label is a constructor of another sort than the one expected.
+    WrongSort {
+        label: String,
+        expected: String,
+        found: String,
+    },
+    /// The node has another number of kids than its constructor.
+    WrongArity {
+        label: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ViolationKind::UnknownLabel { label, sort } => {
+                write!(f, "{label:?} is not a {sort}")
+            }
+            ViolationKind::WrongSort {
+                label,
+                expected,
+                found,
+            } => write!(f, "{label:?} is a {found}, but a {expected} is expected"),
+            ViolationKind::WrongArity {
+                label,
+                expected,
+                found,
+            } => write!(f, "{label:?} takes {expected} kids, but has {found}"),
+        }
+    }
+}
+
+/// A schema for a small language of statements and arithmetic, to try schemas
+/// out with.
+pub fn example() -> Schema {
+    Schema {
+        root: "Program".to_owned(),
+        sorts: vec![
+            Sort::mk("Program"),
+            Sort::mk("Stmt"),
+            Sort::atoms("Expr"),
+            Sort::atoms("Name"),
+        ],
+        constructors: vec![
+            Constructor::many("program", "Program", "Stmt"),
+            Constructor::mk("let", "Stmt", &["Name", "Expr"]),
+            Constructor::mk("print", "Stmt", &["Expr"]),
+            Constructor::mk("+", "Expr", &["Expr", "Expr"]),
+            Constructor::mk("*", "Expr", &["Expr", "Expr"]),
+            Constructor::mk("neg", "Expr", &["Expr"]),
+        ],
+        grammar: grammar::example(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::sexpr;
+
+    fn validate(text: &str) -> Vec<Violation> {
+        example().validate(&sexpr::parse(text).unwrap())
+    }
+
+    #[test]
+    fn schema_validate_test1() {
+        assert_eq!(
+            validate("(program (let x (+ 1 ?)) (print (neg x)))"),
+            vec![]
+        );
+        assert_eq!(validate("(program)"), vec![]);
+        assert_eq!(validate("?"), vec![]);
+        assert_eq!(validate("(program (print ?Expr))"), vec![]);
+        assert_eq!(
+            validate("(program ?Expr)"),
+            vec![Violation {
+                index: Index(vec![0]),
+                kind: ViolationKind::WrongSort {
+                    label: "?Expr".to_owned(),
+                    expected: "Stmt".to_owned(),
+                    found: "Expr".to_owned(),
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn schema_validate_test2() {
+        let violations = validate("(program (let (+ 1 2) 3 4) (print x) foo (+ 1))");
+
+        assert_eq!(
+            violations,
+            vec![
+                Violation {
+                    index: Index(vec![0]),
+                    kind: ViolationKind::WrongArity {
+                        label: "let".to_owned(),
+                        expected: 2,
+                        found: 3,
+                    },
+                },
+                Violation {
+                    index: Index(vec![0, 0]),
+                    kind: ViolationKind::WrongSort {
+                        label: "+".to_owned(),
+                        expected: "Name".to_owned(),
+                        found: "Expr".to_owned(),
+                    },
+                },
+                Violation {
+                    index: Index(vec![2]),
+                    kind: ViolationKind::UnknownLabel {
+                        label: "foo".to_owned(),
+                        sort: "Stmt".to_owned(),
+                    },
+                },
+                Violation {
+                    index: Index(vec![3]),
+                    kind: ViolationKind::WrongSort {
+                        label: "+".to_owned(),
+                        expected: "Stmt".to_owned(),
+                        found: "Expr".to_owned(),
+                    },
+                },
+                Violation {
+                    index: Index(vec![3]),
+                    kind: ViolationKind::WrongArity {
+                        label: "+".to_owned(),
+                        expected: 2,
+                        found: 1,
+                    },
+                },
+            ]
+        );
+        assert_eq!(violations[0].to_string(), "\"let\" takes 2 kids, but has 3");
+    }
+
+    #[test]
+    fn schema_fill_hole_test1() {
+        let schema = example();
+        let mut tree = sexpr::parse("(program (print ?) ?Stmt)").unwrap();
+
+        assert_eq!(schema.sort_at(&tree, &Index(vec![0, 0])), Some("Expr"));
+        assert_eq!(schema.sort_at(&tree, &Index(vec![1])), Some("Stmt"));
+        let labels: Vec<_> = schema
+            .constructors_of("Stmt")
+            .map(|constructor| constructor.label.as_str())
+            .collect();
+        assert_eq!(labels, ["let", "print"]);
+
+        let constructor = schema.constructor("let", "Stmt").unwrap();
+        tree.kids[1] = constructor.instantiate();
+        assert_eq!(
+            sexpr::to_string(&tree),
+            "(program (print ?) (let ?Name ?Expr))"
+        );
+        assert_eq!(schema.validate(&tree), vec![]);
+    }
+
+    #[test]
+    fn schema_completions_test1() {
+        let schema = example();
+        let tree = sexpr::parse("(program (print (+ x ?)) ?)").unwrap();
+        let texts = |index: Vec<usize>| -> Vec<String> {
+            schema
+                .completions(&tree, &Index(index))
+                .iter()
+                .map(Completion::text)
+                .collect()
+        };
+
+        assert_eq!(texts(vec![1]), ["(let ?Name ?Expr)", "(print ?Expr)"]);
+        assert_eq!(
+            texts(vec![0, 0, 0]),
+            [
+                "(+ ?Expr ?Expr)",
+                "(* ?Expr ?Expr)",
+                "(neg ?Expr)",
+                "(+ ● ?Expr)",
+                "(+ ?Expr ●)",
+                "(* ● ?Expr)",
+                "(* ?Expr ●)",
+                "(neg ●)",
+            ]
+        );
+        assert_eq!(texts(vec![]), ["program"]);
+
+        let mut wrapped = tree.clone();
+        let Completion::Wrap(constructor, slot) = &schema.completions(&tree, &Index(vec![0, 0]))[3]
+        else {
+            panic!("expected a wrap");
+        };
+        wrapped.wrap_with_path_at_index(&Index(vec![0, 0]), vec![constructor.tooth(*slot)]);
+        assert_eq!(schema.validate(&wrapped), vec![]);
+    }
+}
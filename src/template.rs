@@ -0,0 +1,36 @@
+//! Named [`Path`]s that the focus can be wrapped with.
+
+use crate::tree::{Path, Tooth, Tree};
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Template {
+    pub name: String,
+    pub path: Path,
+}
+
+impl Template {
+    pub fn mk(name: &str, path: Path) -> Self {
+        Self {
+            name: name.to_owned(),
+            path,
+        }
+    }
+}
+
+/// The templates a new app starts out with.
+pub fn default_templates() -> Vec<Template> {
+    vec![
+        Template::mk(
+            "A",
+            vec![Tooth::mk("A", &[Tree::mk("B", &[])], &[Tree::mk("C", &[])])],
+        ),
+        Template::mk("pair", vec![Tooth::mk("pair", &[], &[Tree::hole()])]),
+        Template::mk(
+            "nested",
+            vec![
+                Tooth::mk("outer", &[Tree::hole()], &[]),
+                Tooth::mk("inner", &[], &[Tree::hole()]),
+            ],
+        ),
+    ]
+}
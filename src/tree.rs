@@ -1,5 +1,25 @@
+mod edit;
+mod error;
+pub mod export;
+mod fold;
+mod hole;
+mod id;
+pub mod layout;
+pub mod sexpr;
+mod zipper;
+
+pub use error::Error;
+pub use fold::Folds;
+pub use hole::{HOLE_LABEL, is_hole_label, is_sort_name};
+pub use id::{NodeId, NodeIds};
+pub use zipper::Zipper;
+
 pub type Step = usize;
 
+/// Key/value metadata attached to a node. The keys are kept sorted, so that
+/// the attributes are always written in the same order.
+pub type Attrs = std::collections::BTreeMap<String, String>;
+
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
 pub struct Index(pub Vec<Step>);
 
@@ -8,6 +28,10 @@ impl Index {
         self.0.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     pub fn push(&mut self, step: Step) {
         self.0.push(step);
     }
@@ -37,6 +61,11 @@ impl Index {
         self.0.pop()
     }
 
+    /// Whether `self` is an ancestor of (or the same as) `other`.
+    pub fn is_prefix_of(&self, other: &Self) -> bool {
+        other.0.starts_with(&self.0)
+    }
+
     pub fn move_up_unsafe(&mut self) {
         self.0.pop();
     }
@@ -57,47 +86,50 @@ impl Index {
         }
     }
 
-    pub fn move_up(&mut self) -> Result<(), String> {
-        self.0.pop().ok_or("can't move up".to_string())?;
+    pub fn move_up(&mut self) -> Result<(), Error> {
+        self.0.pop().ok_or(Error::AtRoot)?;
         Ok(())
     }
 
-    pub fn move_down(&mut self, tree: &Tree, step: Step) -> Result<(), String> {
+    pub fn move_down<L>(&mut self, tree: &Tree<L>, step: Step) -> Result<(), Error> {
         let here = tree.at_index(self)?;
-        if !(step < here.kids.len()) {
-            return Err(format!("can't move down"));
+        if step >= here.kids.len() {
+            let mut index = self.clone();
+            index.push(step);
+            return Err(Error::OutOfBounds {
+                depth: self.len(),
+                index,
+                len: here.kids.len(),
+            });
         };
         self.push(step);
         Ok(())
     }
 
-    pub fn move_left(&mut self, tree: &Tree) -> Result<(), String> {
-        let step = self
-            .pop()
-            .ok_or_else(|| format!("can't pop to move left"))?;
-        if step == 0 {
-            return Err(format!("can't move left"));
+    pub fn move_left<L>(&mut self, tree: &Tree<L>) -> Result<(), Error> {
+        tree.at_index(self)?;
+        match self.0.last() {
+            None => Err(Error::AtRoot),
+            Some(0) => Err(Error::NoSibling),
+            Some(_) => {
+                self.move_left_sibling_unsafe();
+                Ok(())
+            }
+        }
+    }
+
+    pub fn move_right<L>(&mut self, tree: &Tree<L>) -> Result<(), Error> {
+        tree.at_index(self)?;
+        let (parent, step) = self.split_last().ok_or(Error::AtRoot)?;
+        if step + 1 >= tree.at_index_unsafe(&parent).kids.len() {
+            return Err(Error::NoSibling);
         }
-        self.move_down(tree, step - 1).or_else(|_| {
-            self.move_down_unsafe(step);
-            Err(format!("can't move left"))
-        })
-    }
-
-    pub fn move_right(&mut self, tree: &Tree) -> Result<(), String> {
-        let step = self
-            .pop()
-            .ok_or_else(|| format!("can't pop to move right"))?;
-        self.move_down(tree, step + 1).or_else(|_| {
-            self.move_down_unsafe(step);
-            Err(format!("can't move right"))
-        })
-    }
-
-    pub fn move_prev(&mut self, tree: &Tree) -> Result<(), String> {
-        let step = self
-            .pop()
-            .ok_or_else(|| format!("can't pop to move prev"))?;
+        self.move_right_sibling_unsafe();
+        Ok(())
+    }
+
+    pub fn move_prev<L>(&mut self, tree: &Tree<L>) -> Result<(), Error> {
+        let step = self.pop().ok_or(Error::AtRoot)?;
         if step == 0 {
             Ok(())
         } else {
@@ -106,9 +138,9 @@ impl Index {
         }
     }
 
-    pub fn move_down_right_corner(&mut self, tree: &Tree) -> Result<(), String> {
+    pub fn move_down_right_corner<L>(&mut self, tree: &Tree<L>) -> Result<(), Error> {
         let here = tree.at_index(self)?;
-        if here.kids.len() > 0 {
+        if !here.kids.is_empty() {
             self.move_down(tree, here.kids.len() - 1)?;
             self.move_down_right_corner(tree)
         } else {
@@ -116,84 +148,111 @@ impl Index {
         }
     }
 
-    pub fn move_next(&mut self, tree: &Tree) -> Result<(), String> {
+    pub fn move_next<L>(&mut self, tree: &Tree<L>) -> Result<(), Error> {
         let here = tree.at_index(self)?;
-        if 0 < here.kids.len() {
+        if !here.kids.is_empty() {
             self.move_down(tree, 0)
         } else {
             self.move_up_until_right(tree)
         }
     }
 
-    pub fn move_up_until_right(&mut self, tree: &Tree) -> Result<(), String> {
-        self.move_right(tree).or_else(|_| {
-            let step = self
-                .pop()
-                .ok_or_else(|| format!("can't pop to move up until right"))?;
-            self.move_up_until_right(tree).or_else(|_| {
-                self.move_down_unsafe(step);
-                Err(format!("can't move up until right"))
-            })
-        })
+    pub fn move_up_until_right<L>(&mut self, tree: &Tree<L>) -> Result<(), Error> {
+        match self.move_right(tree) {
+            Err(Error::NoSibling) => {
+                let step = self.pop().ok_or(Error::AtEnd)?;
+                self.move_up_until_right(tree)
+                    .inspect_err(|_err| self.move_down_unsafe(step))
+            }
+            Err(Error::AtRoot) => Err(Error::AtEnd),
+            result => result,
+        }
     }
 }
 
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize, PartialEq)]
-pub struct Tree {
-    pub label: String,
-    pub kids: Vec<Tree>,
+impl<'a> IntoIterator for &'a Index {
+    type Item = &'a Step;
+    type IntoIter = std::slice::Iter<'a, Step>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A tree with labels of type `L`, which are strings unless said otherwise.
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+pub struct Tree<L = String> {
+    pub label: L,
+    pub kids: Vec<Tree<L>>,
+    /// Metadata about the node, such as comments, types, source spans or
+    /// colors, by key.
+    #[serde(default, skip_serializing_if = "Attrs::is_empty")]
+    pub attrs: Attrs,
+    /// Whether the kids are hidden in the views. This is kept on the node
+    /// itself, so that it stays with the node wherever edits move it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub folded: bool,
+    /// The identity of the node, which stays the same when edits move it. New
+    /// nodes have none until they are recorded in the [`NodeIds`] of the tree
+    /// that they are put into.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<NodeId>,
 }
 
 impl Tree {
-    pub fn mk(label: &str, kids: &[Tree]) -> Tree {
-        Tree {
-            label: label.to_string(),
-            kids: kids.to_vec(),
-        }
+    pub fn mk(label: &str, kids: &[Self]) -> Self {
+        Self::new(label.to_owned(), kids.to_vec())
     }
+}
 
-    pub fn at_index_unsafe(&self, index: &Index) -> &Tree {
-        fn go<'a>(tree: &'a Tree, index: &Index, i: usize) -> &'a Tree {
-            if i == index.len() {
-                tree
-            } else {
-                go(&tree.kids[i], index, i + 1)
-            }
+impl<L> Tree<L> {
+    pub fn new(label: L, kids: Vec<Self>) -> Self {
+        Self {
+            label,
+            kids,
+            attrs: Attrs::new(),
+            folded: false,
+            id: None,
         }
+    }
 
-        go(self, index, 0)
+    /// The number of nodes in the tree.
+    pub fn size(&self) -> usize {
+        1 + self.kids.iter().map(Self::size).sum::<usize>()
     }
 
-    pub fn at_index(&self, index: &Index) -> Result<&Tree, String> {
-        fn go<'a>(tree: &'a Tree, index: &Index, i: usize) -> Result<&'a Tree, String> {
-            if i == index.len() {
-                Ok(tree)
-            } else {
-                let step = index.get(i).ok_or_else(|| "invalid Step index in Index:\n  - tree = {tree:?}\n  - index = {index:?}\n  - i = {i:?}")?;
-                go(
-                    tree.kids.get(step).ok_or_else(|| format!("invalid Step in Index:\n  - tree = {tree:?}\n  - index = {index:?}\n  - i = {i:?}"))?,
-                    index,
-                    i + 1,
-                )
-            }
-        }
+    pub fn at_index_unsafe(&self, index: &Index) -> &Self {
+        index.iter().fold(self, |tree, step| &tree.kids[*step])
+    }
 
-        go(self, index, 0)
+    pub fn at_index(&self, index: &Index) -> Result<&Self, Error> {
+        let mut tree = self;
+        for (depth, step) in index.iter().enumerate() {
+            tree = tree.kids.get(*step).ok_or_else(|| Error::OutOfBounds {
+                index: index.clone(),
+                depth,
+                len: tree.kids.len(),
+            })?;
+        }
+        Ok(tree)
     }
 
     pub fn is_index_in_bounds(&self, index: &Index) -> bool {
         let mut tree = self;
-        for i in index.iter() {
-            if !(*i < tree.kids.len()) {
+        for i in index {
+            if *i >= tree.kids.len() {
                 return false;
             }
-            tree = &tree.kids[*i]
+            tree = &tree.kids[*i];
         }
-        return true;
+        true
     }
 
-    pub fn wrap_with_path_at_index(&mut self, index: &Index, path: Path) {
-        fn go(tree: &Tree, index: &Index, path: Path, i: usize) -> Tree {
+    pub fn wrap_with_path_at_index(&mut self, index: &Index, path: Path<L>)
+    where
+        L: Clone,
+    {
+        fn go<L: Clone>(tree: &Tree<L>, index: &Index, path: Path<L>, i: usize) -> Tree<L> {
             if let Some(step) = index.get(i) {
                 let kids_left = &tree.kids[..step];
                 let kids_right = if step + 1 < tree.kids.len() {
@@ -205,6 +264,9 @@ impl Tree {
                 Tree {
                     label: tree.label.clone(),
                     kids: [kids_left, &[kid_middle], kids_right].concat(),
+                    attrs: tree.attrs.clone(),
+                    folded: tree.folded,
+                    id: tree.id,
                 }
             } else {
                 tree.clone().wrap_with_path(path)
@@ -214,38 +276,73 @@ impl Tree {
         *self = go(self, index, path, 0);
     }
 
-    pub fn wrap_with_path(self, path: Path) -> Tree {
+    pub fn wrap_with_path(self, path: Path<L>) -> Self {
         let mut tree = self;
         for tooth in path.into_iter().rev() {
-            tree = tree.wrap_with_tooth(tooth)
+            tree = tree.wrap_with_tooth(tooth);
         }
         tree
     }
 
-    pub fn wrap_with_tooth(self, tooth: Tooth) -> Tree {
-        Tree {
+    pub fn wrap_with_tooth(self, tooth: Tooth<L>) -> Self {
+        let mut kids = tooth.kids_left;
+        kids.push(self);
+        kids.extend(tooth.kids_right);
+        Self {
             label: tooth.label,
-            kids: [tooth.kids_left, vec![self], tooth.kids_right].concat(),
+            kids,
+            attrs: tooth.attrs,
+            folded: tooth.folded,
+            id: tooth.id,
         }
     }
+
+    /// Inverse of [`Self::wrap_with_path_at_index`]: cuts out the context
+    /// between `outer` and `inner` and puts the subtree at `inner` in its
+    /// place. Returns the context that was cut out, so that wrapping the
+    /// subtree now at `outer` with it restores the original tree.
+    pub fn unwrap_path_at_index(&mut self, outer: &Index, inner: &Index) -> Result<Path<L>, Error>
+    where
+        L: Default,
+    {
+        if !outer.is_prefix_of(inner) {
+            return Err(Error::NotAnAncestor {
+                outer: outer.clone(),
+                inner: inner.clone(),
+            });
+        }
+        self.at_index(inner)?;
+        let zipper = Zipper::from_tree_at_index(std::mem::take(self), inner)?;
+        let (mut path, focus) = zipper.into_path_and_focus();
+        let inner_path = path.split_off(outer.len());
+        *self = focus.wrap_with_path(path);
+        Ok(inner_path)
+    }
+
+    /// Inverse of [`Self::wrap_with_tooth`] at an index: replaces the parent
+    /// of the subtree at `index` with that subtree, and returns the parent's
+    /// [`Tooth`].
+    pub fn unwrap_tooth_at_index(&mut self, index: &Index) -> Result<Tooth<L>, Error>
+    where
+        L: Default,
+    {
+        let mut outer = index.clone();
+        outer.pop().ok_or(Error::AtRoot)?;
+        let mut path = self.unwrap_path_at_index(&outer, index)?;
+        path.pop().ok_or(Error::AtRoot)
+    }
 }
 
 pub fn big_tree(width: u32, height: u32) -> Tree {
     fn go(current_depth: u32, width: u32, height: u32) -> Tree {
         if current_depth == height {
-            Tree {
-                label: format!("D{current_depth}"),
-                kids: vec![],
-            }
+            Tree::mk(&format!("D{current_depth}"), &[])
         } else {
             let mut kids = Vec::with_capacity(width as usize);
             for _ in 0..width {
                 kids.push(go(current_depth + 1, width, height));
             }
-            Tree {
-                label: format!("N{current_depth}"),
-                kids,
-            }
+            Tree::new(format!("N{current_depth}"), kids)
         }
     }
 
@@ -253,26 +350,61 @@ pub fn big_tree(width: u32, height: u32) -> Tree {
     go(0, width, height)
 }
 
-pub type Path = Vec<Tooth>;
+/// The tree `(A (B D E) C)`, which the tests share.
+#[cfg(test)]
+pub(crate) fn example() -> Tree {
+    Tree::mk(
+        "A",
+        &[
+            Tree::mk("B", &[Tree::mk("D", &[]), Tree::mk("E", &[])]),
+            Tree::mk("C", &[]),
+        ],
+    )
+}
 
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
-pub struct Tooth {
-    pub label: String,
-    pub kids_left: Vec<Tree>,
-    pub kids_right: Vec<Tree>,
+pub type Path<L = String> = Vec<Tooth<L>>;
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
+pub struct Tooth<L = String> {
+    pub label: L,
+    pub kids_left: Vec<Tree<L>>,
+    pub kids_right: Vec<Tree<L>>,
+    /// The attributes of the node that this is the context of.
+    #[serde(default, skip_serializing_if = "Attrs::is_empty")]
+    pub attrs: Attrs,
+    /// Whether the node that this is the context of is folded.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub folded: bool,
+    /// The identity of the node that this is the context of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<NodeId>,
 }
 
 impl Tooth {
     pub fn mk(label: &str, kids_left: &[Tree], kids_right: &[Tree]) -> Self {
-        Tooth {
-            label: label.to_string(),
-            kids_left: kids_left.to_vec(),
-            kids_right: kids_right.to_vec(),
+        Self::new(label.to_owned(), kids_left.to_vec(), kids_right.to_vec())
+    }
+}
+
+impl<L> Tooth<L> {
+    pub fn new(label: L, kids_left: Vec<Tree<L>>, kids_right: Vec<Tree<L>>) -> Self {
+        Self {
+            label,
+            kids_left,
+            kids_right,
+            attrs: Attrs::new(),
+            folded: false,
+            id: None,
         }
     }
 }
 
 #[cfg(test)]
+#[expect(
+    clippy::str_to_string,
+    clippy::semicolon_if_nothing_returned,
+    reason = "the tests are older than the lints"
+)]
 mod tests {
     use super::*;
 
@@ -282,14 +414,11 @@ mod tests {
 
         let tree_new = tree.wrap_with_tooth(Tooth {
             label: "A".to_string(),
-            kids_left: vec![Tree {
-                label: "L".to_string(),
-                kids: vec![],
-            }],
-            kids_right: vec![Tree {
-                label: "R".to_string(),
-                kids: vec![],
-            }],
+            kids_left: vec![Tree::mk("L", &[])],
+            kids_right: vec![Tree::mk("R", &[])],
+            attrs: Attrs::new(),
+            folded: false,
+            id: None,
         });
 
         assert_eq!(
@@ -393,4 +522,153 @@ mod tests {
             )
         )
     }
+
+    #[test]
+    fn tree_unwrap_path_at_test1() {
+        let tree = Tree::mk("B", &[]);
+        let path = vec![Tooth::mk("A", &[Tree::mk("L", &[])], &[Tree::mk("R", &[])])];
+
+        let mut tree_new = tree.clone();
+        tree_new.wrap_with_path_at_index(&Index(vec![]), path.clone());
+        let path_new = tree_new
+            .unwrap_path_at_index(&Index(vec![]), &Index(vec![1]))
+            .unwrap();
+
+        assert_eq!(tree_new, tree);
+        assert_eq!(path_new, path);
+    }
+
+    #[test]
+    fn tree_unwrap_path_at_test2() {
+        let tree = Tree::mk("B1", &[Tree::mk("B2", &[])]);
+        let path = vec![
+            Tooth::mk("A1", &[Tree::mk("L", &[])], &[Tree::mk("R", &[])]),
+            Tooth::mk("A2", &[], &[Tree::mk("R", &[])]),
+        ];
+
+        let mut tree_new = tree.clone();
+        tree_new.wrap_with_path_at_index(&Index(vec![0]), path.clone());
+        let path_new = tree_new
+            .unwrap_path_at_index(&Index(vec![0]), &Index(vec![0, 1, 0]))
+            .unwrap();
+
+        assert_eq!(tree_new, tree);
+        assert_eq!(path_new, path);
+    }
+
+    #[test]
+    fn tree_unwrap_path_at_test3() {
+        let mut tree = Tree::mk("A", &[Tree::mk("B", &[]), Tree::mk("C", &[])]);
+
+        assert_eq!(
+            tree.unwrap_path_at_index(&Index(vec![0]), &Index(vec![1])),
+            Err(Error::NotAnAncestor {
+                outer: Index(vec![0]),
+                inner: Index(vec![1])
+            })
+        );
+        assert_eq!(
+            tree.unwrap_path_at_index(&Index(vec![]), &Index(vec![2])),
+            Err(Error::OutOfBounds {
+                index: Index(vec![2]),
+                depth: 0,
+                len: 2
+            })
+        );
+        assert_eq!(
+            tree,
+            Tree::mk("A", &[Tree::mk("B", &[]), Tree::mk("C", &[])])
+        );
+    }
+
+    #[test]
+    fn tree_unwrap_tooth_at_test1() {
+        let mut tree = Tree::mk(
+            "A",
+            &[Tree::mk("L", &[]), Tree::mk("B", &[]), Tree::mk("R", &[])],
+        );
+
+        let tooth = tree.unwrap_tooth_at_index(&Index(vec![1])).unwrap();
+
+        assert_eq!(tree, Tree::mk("B", &[]));
+        assert_eq!(
+            tooth,
+            Tooth::mk("A", &[Tree::mk("L", &[])], &[Tree::mk("R", &[])])
+        );
+        assert_eq!(
+            tree.unwrap_tooth_at_index(&Index(vec![])),
+            Err(Error::AtRoot)
+        );
+    }
+
+    #[test]
+    fn tree_at_index_test1() {
+        let tree = Tree::mk("A", &[Tree::mk("B", &[Tree::mk("C", &[])])]);
+
+        assert_eq!(tree.at_index(&Index(vec![0, 0])), Ok(&Tree::mk("C", &[])));
+        assert_eq!(
+            tree.at_index(&Index(vec![0, 1])),
+            Err(Error::OutOfBounds {
+                index: Index(vec![0, 1]),
+                depth: 1,
+                len: 1
+            })
+        );
+    }
+
+    #[test]
+    fn index_move_test1() {
+        let tree = Tree::mk(
+            "A",
+            &[Tree::mk("B", &[Tree::mk("C", &[])]), Tree::mk("D", &[])],
+        );
+        let mut index = Index(vec![]);
+
+        assert_eq!(index.move_up(), Err(Error::AtRoot));
+        assert_eq!(index.move_left(&tree), Err(Error::AtRoot));
+
+        index.move_next(&tree).unwrap();
+        index.move_next(&tree).unwrap();
+        assert_eq!(index, Index(vec![0, 0]));
+        assert_eq!(index.move_right(&tree), Err(Error::NoSibling));
+        assert_eq!(index.move_left(&tree), Err(Error::NoSibling));
+        assert_eq!(index, Index(vec![0, 0]));
+
+        index.move_next(&tree).unwrap();
+        assert_eq!(index, Index(vec![1]));
+        assert_eq!(index.move_next(&tree), Err(Error::AtEnd));
+        assert_eq!(index, Index(vec![1]));
+
+        index.move_prev(&tree).unwrap();
+        assert_eq!(index, Index(vec![0, 0]));
+        index.move_up().unwrap();
+        index.move_right(&tree).unwrap();
+        assert_eq!(index, Index(vec![1]));
+    }
+
+    #[test]
+    fn tree_attrs_test1() {
+        let mut tree = Tree::mk("A", &[Tree::mk("B", &[])]);
+        tree.attrs.insert("type".to_owned(), "int".to_owned());
+        tree.kids[0]
+            .attrs
+            .insert("comment".to_owned(), "a \"kid\"".to_owned());
+        let mut tooth = Tooth::mk("W", &[], &[]);
+        tooth.attrs.insert("color".to_owned(), "red".to_owned());
+
+        let mut tree_new = tree.clone();
+        tree_new.wrap_with_path_at_index(&Index(vec![0]), vec![tooth.clone()]);
+        assert_eq!(tree_new.attrs, tree.attrs);
+        assert_eq!(tree_new.kids[0].attrs, tooth.attrs);
+        assert_eq!(tree_new.kids[0].kids[0].attrs, tree.kids[0].attrs);
+
+        let json = serde_json::to_string(&tree_new).unwrap();
+        assert_eq!(serde_json::from_str::<Tree>(&json).unwrap(), tree_new);
+
+        let path = tree_new
+            .unwrap_path_at_index(&Index(vec![0]), &Index(vec![0, 0]))
+            .unwrap();
+        assert_eq!(path, vec![tooth]);
+        assert_eq!(tree_new, tree);
+    }
 }
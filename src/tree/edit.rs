@@ -0,0 +1,383 @@
+//! Structural edits on a [`Tree`] addressed by [`Index`].
+//!
+//! Every edit that shifts the positions of other nodes takes the caller's
+//! `focus` and fixes it up, so that it keeps pointing at the same node (or, if
+//! that node was removed, at a nearby one).
+
+use super::{Error, Index, Step, Tree};
+
+impl Index {
+    /// Fixes up `self` after a node was inserted at `at`.
+    pub fn shift_for_insert(&mut self, at: &Self) {
+        let Some((step, parent)) = at.0.split_last() else {
+            return;
+        };
+        let depth = parent.len();
+        if self.len() > depth && self.0.starts_with(parent) && self.0[depth] >= *step {
+            self.0[depth] += 1;
+        }
+    }
+
+    /// Fixes up `self` after the node at `at` was deleted. Returns `false`
+    /// (leaving `self` unchanged) if `self` pointed into the deleted subtree.
+    pub fn shift_for_delete(&mut self, at: &Self) -> bool {
+        if at.is_prefix_of(self) {
+            return false;
+        }
+        let Some((step, parent)) = at.0.split_last() else {
+            return true;
+        };
+        let depth = parent.len();
+        if self.len() > depth && self.0.starts_with(parent) && self.0[depth] > *step {
+            self.0[depth] -= 1;
+        }
+        true
+    }
+
+    /// Splits off the last step, returning the index of the parent and the
+    /// step from the parent to `self`.
+    pub fn split_last(&self) -> Option<(Self, Step)> {
+        let (step, parent) = self.0.split_last()?;
+        Some((Self(parent.to_vec()), *step))
+    }
+}
+
+impl<L> Tree<L> {
+    pub fn at_index_mut(&mut self, index: &Index) -> Result<&mut Self, Error> {
+        let mut tree = self;
+        for (depth, step) in index.iter().enumerate() {
+            let len = tree.kids.len();
+            tree = tree.kids.get_mut(*step).ok_or_else(|| Error::OutOfBounds {
+                index: index.clone(),
+                depth,
+                len,
+            })?;
+        }
+        Ok(tree)
+    }
+
+    /// Inserts `kid` as the kid at `position` of the node at `parent`, and
+    /// returns the index of the inserted kid.
+    pub fn insert_kid_at_index(
+        &mut self,
+        parent: &Index,
+        position: Step,
+        kid: Self,
+        focus: &mut Index,
+    ) -> Result<Index, Error> {
+        let mut index = parent.clone();
+        index.push(position);
+
+        let tree = self.at_index_mut(parent)?;
+        if position > tree.kids.len() {
+            return Err(Error::OutOfBounds {
+                depth: parent.len(),
+                index,
+                len: tree.kids.len(),
+            });
+        }
+        tree.kids.insert(position, kid);
+
+        focus.shift_for_insert(&index);
+        Ok(index)
+    }
+
+    /// Deletes the subtree at `index` and returns it. If `focus` was inside the
+    /// deleted subtree, it moves to the next sibling, or else the previous
+    /// sibling, or else the parent.
+    pub fn delete_at_index(&mut self, index: &Index, focus: &mut Index) -> Result<Self, Error> {
+        let (parent, step) = index.split_last().ok_or(Error::AtRoot)?;
+        self.at_index(index)?;
+        let tree = self.at_index_mut(&parent)?;
+        let deleted = tree.kids.remove(step);
+
+        if !focus.shift_for_delete(index) {
+            *focus = parent;
+            if step < tree.kids.len() {
+                focus.push(step);
+            } else if step > 0 {
+                focus.push(step - 1);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Replaces the subtree at `index` with `tree` and returns the old
+    /// subtree. If `focus` was inside the old subtree, it moves to `index`.
+    pub fn replace_at_index(
+        &mut self,
+        index: &Index,
+        tree: Self,
+        focus: &mut Index,
+    ) -> Result<Self, Error> {
+        let here = self.at_index_mut(index)?;
+        let old = std::mem::replace(here, tree);
+        if index.is_prefix_of(focus) {
+            *focus = index.clone();
+        }
+        Ok(old)
+    }
+
+    /// Replaces the label of the node at `index` and returns the old label.
+    pub fn replace_label_at_index(&mut self, index: &Index, label: L) -> Result<L, Error> {
+        let here = self.at_index_mut(index)?;
+        Ok(std::mem::replace(&mut here.label, label))
+    }
+
+    /// Sets the attribute `key` of the node at `index` to `value`, or removes
+    /// it if `value` is `None`, and returns its old value.
+    pub fn set_attr_at_index(
+        &mut self,
+        index: &Index,
+        key: String,
+        value: Option<String>,
+    ) -> Result<Option<String>, Error> {
+        let here = self.at_index_mut(index)?;
+        Ok(match value {
+            Some(value) => here.attrs.insert(key, value),
+            None => here.attrs.remove(&key),
+        })
+    }
+
+    /// Swaps the subtree at `index` with its sibling at step `other`.
+    pub fn swap_siblings_at_index(
+        &mut self,
+        index: &Index,
+        other: Step,
+        focus: &mut Index,
+    ) -> Result<(), Error> {
+        let (parent, step) = index.split_last().ok_or(Error::AtRoot)?;
+        self.at_index(index)?;
+        let mut index_other = parent.clone();
+        index_other.push(other);
+        self.at_index(&index_other)?;
+        let tree = self.at_index_mut(&parent)?;
+        tree.kids.swap(step, other);
+
+        let depth = parent.len();
+        if focus.len() > depth && parent.is_prefix_of(focus) {
+            if focus.0[depth] == step {
+                focus.0[depth] = other;
+            } else if focus.0[depth] == other {
+                focus.0[depth] = step;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the subtree at `from` to be the kid at `position` of the node at
+    /// `parent`, where `parent` and `position` refer to the tree before the
+    /// move. Returns the new index of the moved subtree.
+    pub fn move_at_index(
+        &mut self,
+        from: &Index,
+        parent: &Index,
+        position: Step,
+        focus: &mut Index,
+    ) -> Result<Index, Error> {
+        if from.is_prefix_of(parent) {
+            return Err(Error::MoveIntoItself {
+                from: from.clone(),
+                parent: parent.clone(),
+            });
+        }
+        self.at_index(from)?;
+        let len = self.at_index(parent)?.kids.len();
+        if position > len {
+            let mut index = parent.clone();
+            index.push(position);
+            return Err(Error::OutOfBounds {
+                depth: parent.len(),
+                index,
+                len,
+            });
+        }
+
+        let mut focus_inside = None;
+        if from.is_prefix_of(focus) {
+            focus_inside = Some(focus.0.split_off(from.len()));
+            *focus = from.clone();
+        }
+
+        let subtree = self.delete_at_index(from, focus)?;
+        let mut to = parent.clone();
+        to.push(position);
+        to.shift_for_delete(from);
+        let (to_parent, to_position) = (Index(to.0[..parent.len()].to_vec()), to.0[parent.len()]);
+        let index = self.insert_kid_at_index(&to_parent, to_position, subtree, focus)?;
+
+        if let Some(rest) = focus_inside {
+            *focus = index.clone();
+            focus.0.extend(rest);
+        }
+        Ok(index)
+    }
+
+    /// Inserts a copy of the subtree at `index` right after it, and returns
+    /// the index of the copy. The nodes of the copy have no ids.
+    pub fn duplicate_at_index(&mut self, index: &Index, focus: &mut Index) -> Result<Index, Error>
+    where
+        L: Clone,
+    {
+        let (parent, step) = index.split_last().ok_or(Error::AtRoot)?;
+        let mut copy = self.at_index(index)?.clone();
+        copy.clear_ids();
+        self.insert_kid_at_index(&parent, step + 1, copy, focus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::example;
+
+    #[test]
+    fn tree_insert_kid_at_index_test1() {
+        let mut tree = example();
+        let mut focus = Index(vec![1]);
+
+        let index = tree
+            .insert_kid_at_index(&Index(vec![]), 1, Tree::mk("X", &[]), &mut focus)
+            .unwrap();
+
+        assert_eq!(index, Index(vec![1]));
+        assert_eq!(focus, Index(vec![2]));
+        assert_eq!(tree.at_index(&focus).unwrap().label, "C");
+        assert_eq!(
+            tree.insert_kid_at_index(&Index(vec![]), 4, Tree::mk("X", &[]), &mut focus),
+            Err(Error::OutOfBounds {
+                index: Index(vec![4]),
+                depth: 0,
+                len: 3
+            })
+        );
+    }
+
+    #[test]
+    fn tree_delete_at_index_test1() {
+        let mut tree = example();
+        let mut focus = Index(vec![1]);
+
+        let deleted = tree.delete_at_index(&Index(vec![0]), &mut focus).unwrap();
+
+        assert_eq!(deleted.label, "B");
+        assert_eq!(tree, Tree::mk("A", &[Tree::mk("C", &[])]));
+        assert_eq!(focus, Index(vec![0]));
+        assert_eq!(
+            tree.delete_at_index(&Index(vec![]), &mut focus),
+            Err(Error::AtRoot)
+        );
+    }
+
+    #[test]
+    fn tree_delete_at_index_test2() {
+        let mut tree = example();
+
+        let mut focus = Index(vec![0, 1]);
+        tree.delete_at_index(&Index(vec![0, 1]), &mut focus)
+            .unwrap();
+        assert_eq!(focus, Index(vec![0, 0]));
+
+        let mut focus = Index(vec![0, 0]);
+        tree.delete_at_index(&Index(vec![0, 0]), &mut focus)
+            .unwrap();
+        assert_eq!(focus, Index(vec![0]));
+    }
+
+    #[test]
+    fn tree_replace_at_index_test1() {
+        let mut tree = example();
+        let mut focus = Index(vec![0, 1]);
+
+        let old = tree
+            .replace_at_index(&Index(vec![0]), Tree::mk("X", &[]), &mut focus)
+            .unwrap();
+
+        assert_eq!(old, example().kids[0]);
+        assert_eq!(focus, Index(vec![0]));
+        assert_eq!(tree.at_index(&focus).unwrap().label, "X");
+    }
+
+    #[test]
+    fn tree_replace_label_at_index_test1() {
+        let mut tree = example();
+
+        let old = tree
+            .replace_label_at_index(&Index(vec![1]), "X".to_owned())
+            .unwrap();
+
+        assert_eq!(old, "C");
+        assert_eq!(tree.kids[1].label, "X");
+    }
+
+    #[test]
+    fn tree_swap_siblings_at_index_test1() {
+        let mut tree = example();
+        let mut focus = Index(vec![0, 1]);
+
+        tree.swap_siblings_at_index(&Index(vec![0]), 1, &mut focus)
+            .unwrap();
+
+        assert_eq!(focus, Index(vec![1, 1]));
+        assert_eq!(tree.at_index(&focus).unwrap().label, "E");
+        assert_eq!(tree.kids[0].label, "C");
+    }
+
+    #[test]
+    fn tree_move_at_index_test1() {
+        let mut tree = example();
+        let mut focus = Index(vec![1]);
+
+        let index = tree
+            .move_at_index(&Index(vec![1]), &Index(vec![0]), 1, &mut focus)
+            .unwrap();
+
+        assert_eq!(index, Index(vec![0, 1]));
+        assert_eq!(focus, index);
+        assert_eq!(
+            tree,
+            Tree::mk(
+                "A",
+                &[Tree::mk(
+                    "B",
+                    &[Tree::mk("D", &[]), Tree::mk("C", &[]), Tree::mk("E", &[])]
+                )]
+            )
+        );
+    }
+
+    #[test]
+    fn tree_move_at_index_test2() {
+        let mut tree = example();
+        let mut focus = Index(vec![0, 1]);
+
+        let index = tree
+            .move_at_index(&Index(vec![0]), &Index(vec![]), 2, &mut focus)
+            .unwrap();
+
+        assert_eq!(index, Index(vec![1]));
+        assert_eq!(focus, Index(vec![1, 1]));
+        assert_eq!(tree.at_index(&focus).unwrap().label, "E");
+        assert_eq!(
+            tree.move_at_index(&Index(vec![1]), &Index(vec![1, 0]), 0, &mut focus),
+            Err(Error::MoveIntoItself {
+                from: Index(vec![1]),
+                parent: Index(vec![1, 0])
+            })
+        );
+    }
+
+    #[test]
+    fn tree_duplicate_at_index_test1() {
+        let mut tree = example();
+        let mut focus = Index(vec![1]);
+
+        let index = tree
+            .duplicate_at_index(&Index(vec![0]), &mut focus)
+            .unwrap();
+
+        assert_eq!(index, Index(vec![1]));
+        assert_eq!(tree.kids[0], tree.kids[1]);
+        assert_eq!(focus, Index(vec![2]));
+    }
+}
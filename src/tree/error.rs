@@ -0,0 +1,59 @@
+use super::Index;
+
+/// Why a navigation or edit on a [`super::Tree`] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The step at `depth` in `index` is out of bounds of the `len` kids of
+    /// the node there.
+    OutOfBounds {
+        index: Index,
+        depth: usize,
+        len: usize,
+    },
+
+    /// The operation needs a parent, but it was asked of the root.
+    AtRoot,
+
+    /// There is no sibling in the direction of the move.
+    NoSibling,
+
+    /// There is no node after the last one in preorder.
+    AtEnd,
+
+    /// There is no hole in the direction of the move.
+    NoHole,
+
+    /// `outer` was expected to be an ancestor of `inner`.
+    NotAnAncestor { outer: Index, inner: Index },
+
+    /// A subtree can't be moved to somewhere inside of itself.
+    MoveIntoItself { from: Index, parent: Index },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OutOfBounds { index, depth, len } => write!(
+                f,
+                "step {depth} of index {:?} is out of bounds of {len} kids",
+                index.0
+            ),
+            Self::AtRoot => write!(f, "there is no parent of the root"),
+            Self::NoSibling => write!(f, "there is no sibling in that direction"),
+            Self::AtEnd => write!(f, "there is no node after the last one"),
+            Self::NoHole => write!(f, "there is no hole in that direction"),
+            Self::NotAnAncestor { outer, inner } => write!(
+                f,
+                "index {:?} is not an ancestor of index {:?}",
+                outer.0, inner.0
+            ),
+            Self::MoveIntoItself { from, parent } => write!(
+                f,
+                "can't move index {:?} into index {:?} inside of itself",
+                from.0, parent.0
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,271 @@
+//! Exporting a [`Tree`] as a Graphviz DOT or Mermaid flowchart diagram, or as
+//! an SVG picture.
+//!
+//! Labels are written as they are displayed, and the attributes of a node as
+//! a tooltip, or as comments in Mermaid, which has no tooltips without links.
+
+use super::{Attrs, Index, Tree};
+use std::fmt::Display;
+
+pub mod svg;
+
+pub use svg::to_svg;
+
+/// What to include in an exported diagram.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// The node to highlight, if any.
+    pub focus: Option<Index>,
+    /// The depth below which nodes are left out, if any. The root has depth 0.
+    /// The kids that are left out of a node are drawn as a single placeholder
+    /// that says how many nodes were left out.
+    pub max_depth: Option<usize>,
+}
+
+/// A node of the diagram.
+enum Node {
+    Tree {
+        label: String,
+        attrs: Attrs,
+        is_focus: bool,
+    },
+    /// Stands in for `count` nodes that were left out.
+    Elided { count: usize },
+}
+
+impl Node {
+    fn label(&self) -> String {
+        match self {
+            Self::Tree { label, .. } => label.clone(),
+            Self::Elided { count } => format!("… {count} more"),
+        }
+    }
+
+    fn attrs(&self) -> Option<&Attrs> {
+        match self {
+            Self::Tree { attrs, .. } if !attrs.is_empty() => Some(attrs),
+            _ => None,
+        }
+    }
+}
+
+/// The attributes as lines of `key: value`.
+fn attrs_text(attrs: &Attrs) -> String {
+    attrs
+        .iter()
+        .map(|(key, value)| format!("{key}: {value}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The nodes of the diagram of `tree` in preorder, each with the position of
+/// its parent.
+fn nodes<L: Display>(tree: &Tree<L>, options: &Options) -> Vec<(Option<usize>, Node)> {
+    fn go<L: Display>(
+        tree: &Tree<L>,
+        index: &mut Index,
+        parent: Option<usize>,
+        options: &Options,
+        nodes: &mut Vec<(Option<usize>, Node)>,
+    ) {
+        let is_focus = options.focus.as_ref() == Some(index);
+        let id = nodes.len();
+        nodes.push((
+            parent,
+            Node::Tree {
+                label: tree.label.to_string(),
+                attrs: tree.attrs.clone(),
+                is_focus,
+            },
+        ));
+        if tree.kids.is_empty() {
+            return;
+        }
+        if options.max_depth.is_some_and(|depth| index.len() >= depth) {
+            let count = tree.kids.iter().map(Tree::<L>::size).sum();
+            nodes.push((Some(id), Node::Elided { count }));
+            return;
+        }
+        for (i, kid) in tree.kids.iter().enumerate() {
+            index.push(i);
+            go(kid, index, Some(id), options, nodes);
+            index.pop();
+        }
+    }
+
+    let mut nodes = vec![];
+    go(tree, &mut Index::default(), None, options, &mut nodes);
+    nodes
+}
+
+/// Exports `tree` as a Graphviz DOT digraph.
+pub fn to_dot<L: Display>(tree: &Tree<L>, options: &Options) -> String {
+    let mut string = String::from("digraph tree {\n    node [shape=box];\n");
+    for (id, (parent, node)) in nodes(tree, options).iter().enumerate() {
+        let label = escape_dot(&node.label());
+        let style = match node {
+            Node::Tree { is_focus: true, .. } => ", color=red, penwidth=2",
+            Node::Tree { .. } => "",
+            Node::Elided { .. } => ", style=dashed",
+        };
+        let tooltip = node.attrs().map_or_else(String::new, |attrs| {
+            format!(", tooltip=\"{}\"", escape_dot(&attrs_text(attrs)))
+        });
+        string.push_str(&format!("    n{id} [label=\"{label}\"{style}{tooltip}];\n"));
+        if let Some(parent) = parent {
+            string.push_str(&format!("    n{parent} -> n{id};\n"));
+        }
+    }
+    string.push_str("}\n");
+    string
+}
+
+/// Exports `tree` as a top-down Mermaid flowchart.
+pub fn to_mermaid<L: Display>(tree: &Tree<L>, options: &Options) -> String {
+    let mut string = String::from("flowchart TD\n");
+    let mut styles = String::new();
+    for (id, (parent, node)) in nodes(tree, options).iter().enumerate() {
+        let label = escape_mermaid(&node.label());
+        string.push_str(&format!("    n{id}[\"{label}\"]\n"));
+        if let Some(attrs) = node.attrs() {
+            // a comment ends at the end of the line, so every line gets one
+            let text = attrs_text(attrs).replace('\n', &format!("\n    %% n{id} "));
+            string.push_str(&format!("    %% n{id} {text}\n"));
+        }
+        if let Some(parent) = parent {
+            string.push_str(&format!("    n{parent} --> n{id}\n"));
+        }
+        match node {
+            Node::Tree { is_focus: true, .. } => {
+                styles.push_str(&format!("    style n{id} stroke:red,stroke-width:2px\n"));
+            }
+            Node::Tree { .. } => {}
+            Node::Elided { .. } => {
+                styles.push_str(&format!("    style n{id} stroke-dasharray:4\n"));
+            }
+        }
+    }
+    string.push_str(&styles);
+    string
+}
+
+fn escape_dot(label: &str) -> String {
+    let mut string = String::new();
+    for c in label.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            c => string.push(c),
+        }
+    }
+    string
+}
+
+fn escape_mermaid(label: &str) -> String {
+    let mut string = String::new();
+    for c in label.chars() {
+        match c {
+            '"' => string.push_str("#quot;"),
+            '#' => string.push_str("#35;"),
+            '<' => string.push_str("#lt;"),
+            '>' => string.push_str("#gt;"),
+            '\n' => string.push_str("<br>"),
+            c => string.push(c),
+        }
+    }
+    // Mermaid doesn't draw a node with an empty label.
+    if string.is_empty() {
+        string.push(' ');
+    }
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::example;
+
+    #[test]
+    fn export_to_dot_test1() {
+        let options = Options {
+            focus: Some(Index(vec![0])),
+            max_depth: None,
+        };
+
+        assert_eq!(
+            to_dot(&example(), &options),
+            "digraph tree {
+    node [shape=box];
+    n0 [label=\"A\"];
+    n1 [label=\"B\", color=red, penwidth=2];
+    n0 -> n1;
+    n2 [label=\"D\"];
+    n1 -> n2;
+    n3 [label=\"E\"];
+    n1 -> n3;
+    n4 [label=\"C\"];
+    n0 -> n4;
+}
+"
+        );
+    }
+
+    #[test]
+    fn export_to_mermaid_test1() {
+        let options = Options {
+            focus: Some(Index(vec![])),
+            max_depth: Some(1),
+        };
+
+        assert_eq!(
+            to_mermaid(&example(), &options),
+            "flowchart TD
+    n0[\"A\"]
+    n1[\"B\"]
+    n0 --> n1
+    n2[\"… 2 more\"]
+    n1 --> n2
+    n3[\"C\"]
+    n0 --> n3
+    style n0 stroke:red,stroke-width:2px
+    style n2 stroke-dasharray:4
+"
+        );
+    }
+
+    #[test]
+    fn export_escape_test1() {
+        let tree = Tree::mk("say \"#1\"\n<a\\b>", &[]);
+        let options = Options::default();
+
+        assert!(to_dot(&tree, &options).contains(r##"[label="say \"#1\"\n<a\\b>"]"##));
+        assert!(
+            to_mermaid(&tree, &options).contains(r##"["say #quot;#35;1#quot;<br>#lt;a\b#gt;"]"##)
+        );
+    }
+
+    #[test]
+    fn export_attrs_test1() {
+        let mut tree = example();
+        tree.kids[1]
+            .attrs
+            .insert("comment".to_owned(), "two\nlines".to_owned());
+        tree.kids[1]
+            .attrs
+            .insert("type".to_owned(), "<int>".to_owned());
+        let options = Options::default();
+
+        assert!(
+            to_dot(&tree, &options)
+                .contains(r#"n4 [label="C", tooltip="comment: two\nlines\ntype: <int>"];"#)
+        );
+        assert!(to_mermaid(&tree, &options).contains(
+            "    n4[\"C\"]\n    %% n4 comment: two\n    %% n4 lines\n    %% n4 type: <int>\n"
+        ));
+        assert!(
+            svg::to_svg(&tree, &options)
+                .contains("<title>comment: two\nlines\ntype: &lt;int&gt;</title></rect>")
+        );
+    }
+}
@@ -0,0 +1,355 @@
+//! Exporting a [`Tree`] as an SVG picture of the nested boxes that the app
+//! draws it as.
+//!
+//! The boxes are laid out the way the app lays out its frames: each node is a
+//! frame with its label on top and its kids in a row below, and the kids of a
+//! folded node are replaced by a placeholder that says how many are hidden.
+//! The app measures the labels with its font, with [`to_svg_measured`].
+//! Without a window there is no font at hand, so [`to_svg`] assumes that
+//! labels are [`Style::char_width`] wide per character.
+
+use super::Options;
+use crate::tree::{Index, Tree};
+use std::fmt::Display;
+
+/// The sizes and colors of the boxes, shared with the on-screen view.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub inner_margin: f32,
+    pub outer_margin: f32,
+    pub corner_radius: f32,
+    pub stroke_width: f32,
+    /// The space between the label of a node and the row of its kids.
+    pub label_spacing: f32,
+    /// The space between kids.
+    pub kid_spacing: f32,
+    /// The padding to the left and right of the text of a label.
+    pub label_padding: f32,
+    pub label_height: f32,
+    pub font_size: f32,
+    /// The width of a character of a label.
+    pub char_width: f32,
+    pub fill: &'static str,
+    pub stroke: &'static str,
+    pub focus_stroke: &'static str,
+    pub label_fill: &'static str,
+    pub text: &'static str,
+}
+
+impl Style {
+    pub const DEFAULT: Self = Self {
+        inner_margin: 12.0,
+        outer_margin: 12.0,
+        corner_radius: 12.0,
+        stroke_width: 2.0,
+        label_spacing: 3.0,
+        kid_spacing: 8.0,
+        label_padding: 4.0,
+        label_height: 18.0,
+        font_size: 12.5,
+        char_width: 7.0,
+        fill: "#0000ff",
+        stroke: "#000000",
+        focus_stroke: "#ff0000",
+        label_fill: "#3c3c3c",
+        text: "#dcdcdc",
+    };
+
+    /// The distance from the outside of a box to its content.
+    pub fn margin(&self) -> f32 {
+        self.outer_margin + self.stroke_width + self.inner_margin
+    }
+
+    /// The size of a box, including its outer margin, with a label that is
+    /// `label_width` wide and kids of the sizes `row` in a row below it.
+    pub fn box_size(&self, label_width: f32, row: &[[f32; 2]]) -> [f32; 2] {
+        let mut content = [label_width, self.label_height];
+        if !row.is_empty() {
+            let row_width = row.iter().map(|size| size[0]).sum::<f32>()
+                + self.kid_spacing * (row.len() - 1) as f32;
+            let row_height = row.iter().map(|size| size[1]).fold(0.0, f32::max);
+            content[0] = content[0].max(row_width);
+            content[1] += self.label_spacing + row_height;
+        }
+        let margin = 2.0 * self.margin();
+        [content[0] + margin, content[1] + margin]
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The text of the placeholder that stands in for the kids of a folded node.
+pub fn fold_placeholder<L>(tree: &Tree<L>) -> String {
+    format!("… {} hidden", tree.hidden_count())
+}
+
+/// A node with the size of its box, including its outer margin.
+pub(crate) struct Boxed {
+    label: String,
+    label_width: f32,
+    is_focus: bool,
+    /// Whether this stands in for nodes that were left out.
+    is_elided: bool,
+    /// The tooltip of the box, which lists the attributes of the node.
+    title: Option<String>,
+    /// The text and width of the placeholder that stands in for the kids of a
+    /// folded node.
+    pub(crate) placeholder: Option<(String, f32)>,
+    pub(crate) kids: Vec<Boxed>,
+    pub(crate) size: [f32; 2],
+}
+
+impl Boxed {
+    fn new(
+        label: String,
+        is_focus: bool,
+        is_elided: bool,
+        kids: Vec<Self>,
+        style: &Style,
+        text_width: &impl Fn(String) -> f32,
+    ) -> Self {
+        let row: Vec<_> = kids.iter().map(|kid| kid.size).collect();
+        let label_width = text_width(label.clone()) + 2.0 * style.label_padding;
+        let size = style.box_size(label_width, &row);
+        Self {
+            label,
+            label_width,
+            is_focus,
+            is_elided,
+            title: None,
+            placeholder: None,
+            kids,
+            size,
+        }
+    }
+}
+
+/// Lays out the boxes of `tree`, with labels that are as wide as `text_width`
+/// says.
+pub(crate) fn layout<L: Display>(
+    tree: &Tree<L>,
+    index: &mut Index,
+    options: &Options,
+    style: &Style,
+    text_width: &impl Fn(String) -> f32,
+) -> Boxed {
+    let is_focus = options.focus.as_ref() == Some(index);
+    let label = tree.label.to_string();
+    let mut boxed = if options.max_depth.is_some_and(|depth| index.len() >= depth) {
+        let kids = if tree.kids.is_empty() {
+            vec![]
+        } else {
+            let count = tree.kids.iter().map(Tree::size).sum::<usize>();
+            let more = format!("… {count} more");
+            vec![Boxed::new(more, false, true, vec![], style, text_width)]
+        };
+        Boxed::new(label, is_focus, false, kids, style, text_width)
+    } else if tree.is_folded() {
+        let mut boxed = Boxed::new(label, is_focus, false, vec![], style, text_width);
+        let text = fold_placeholder(tree);
+        let width = text_width(text.clone()) + 2.0 * style.label_padding;
+        boxed.size = style.box_size(boxed.label_width, &[[width, style.label_height]]);
+        boxed.placeholder = Some((text, width));
+        boxed
+    } else {
+        let mut kids = vec![];
+        for (i, kid) in tree.kids.iter().enumerate() {
+            index.push(i);
+            kids.push(layout(kid, index, options, style, text_width));
+            index.pop();
+        }
+        Boxed::new(label, is_focus, false, kids, style, text_width)
+    };
+    if !tree.attrs.is_empty() {
+        boxed.title = Some(super::attrs_text(&tree.attrs));
+    }
+    boxed
+}
+
+/// Exports `tree` as a standalone SVG document, drawn with [`Style::DEFAULT`].
+pub fn to_svg<L: Display>(tree: &Tree<L>, options: &Options) -> String {
+    to_svg_with_style(tree, options, &Style::DEFAULT)
+}
+
+/// Exports `tree` as a standalone SVG document, drawn with `style`.
+pub fn to_svg_with_style<L: Display>(tree: &Tree<L>, options: &Options, style: &Style) -> String {
+    let text_width = |text: String| text.chars().count() as f32 * style.char_width;
+    to_svg_measured(tree, options, style, &text_width)
+}
+
+/// Exports `tree` as a standalone SVG document, drawn with `style`, with
+/// labels that are as wide as `text_width` says.
+pub fn to_svg_measured<L: Display>(
+    tree: &Tree<L>,
+    options: &Options,
+    style: &Style,
+    text_width: &impl Fn(String) -> f32,
+) -> String {
+    let boxed = layout(tree, &mut Index::default(), options, style, text_width);
+    let [width, height] = boxed.size;
+    let mut string = String::new();
+    string.push_str(&format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    ));
+    string.push('\n');
+    string.push_str(concat!(
+        "<defs><filter id=\"shadow\">",
+        "<feDropShadow dx=\"4\" dy=\"4\" stdDeviation=\"6\" flood-opacity=\"0.7\"/>",
+        "</filter></defs>\n",
+    ));
+    string.push_str(&format!(
+        r#"<g font-family="sans-serif" font-size="{}">"#,
+        style.font_size
+    ));
+    string.push('\n');
+    draw(&mut string, &boxed, [0.0, 0.0], style);
+    string.push_str("</g>\n</svg>\n");
+    string
+}
+
+/// Draws `boxed` with the top left corner of its outer margin at `position`.
+fn draw(string: &mut String, boxed: &Boxed, position: [f32; 2], style: &Style) {
+    // The stroke is inside of the frame, but SVG centers it on the outline.
+    let inset = style.outer_margin + style.stroke_width / 2.0;
+    let stroke = if boxed.is_focus {
+        style.focus_stroke
+    } else {
+        style.stroke
+    };
+    let dash = if boxed.is_elided {
+        r#" stroke-dasharray="4""#
+    } else {
+        ""
+    };
+    let end = boxed.title.as_ref().map_or_else(
+        || "/>".to_owned(),
+        |title| format!("><title>{}</title></rect>", escape(title)),
+    );
+    string.push_str(&format!(
+        r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" fill="{}" stroke="{stroke}" stroke-width="{}"{dash} filter="url(#shadow)"{end}"#,
+        position[0] + inset,
+        position[1] + inset,
+        boxed.size[0] - 2.0 * inset,
+        boxed.size[1] - 2.0 * inset,
+        style.corner_radius,
+        style.fill,
+        style.stroke_width,
+    ));
+    string.push('\n');
+
+    let x = position[0] + style.margin();
+    let y = position[1] + style.margin();
+    draw_label(string, &boxed.label, boxed.label_width, [x, y], style);
+
+    let mut kid_x = x;
+    let kid_y = y + style.label_height + style.label_spacing;
+    if let Some((text, width)) = &boxed.placeholder {
+        draw_label(string, text, *width, [kid_x, kid_y], style);
+    }
+    for kid in &boxed.kids {
+        draw(string, kid, [kid_x, kid_y], style);
+        kid_x += kid.size[0] + style.kid_spacing;
+    }
+}
+
+/// Draws a label that is `width` wide, with its top left corner at `position`.
+fn draw_label(string: &mut String, label: &str, width: f32, position: [f32; 2], style: &Style) {
+    let [x, y] = position;
+    string.push_str(&format!(
+        r#"<rect x="{x}" y="{y}" width="{width}" height="{}" rx="2" fill="{}"/>"#,
+        style.label_height, style.label_fill,
+    ));
+    string.push('\n');
+    string.push_str(&format!(
+        r#"<text x="{}" y="{}" dominant-baseline="central" fill="{}" xml:space="preserve">{}</text>"#,
+        x + style.label_padding,
+        y + style.label_height / 2.0,
+        style.text,
+        escape(label),
+    ));
+    string.push('\n');
+}
+
+fn escape(label: &str) -> String {
+    let mut string = String::new();
+    for c in label.chars() {
+        match c {
+            '&' => string.push_str("&amp;"),
+            '<' => string.push_str("&lt;"),
+            '>' => string.push_str("&gt;"),
+            '"' => string.push_str("&quot;"),
+            c => string.push(c),
+        }
+    }
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_layout_test1() {
+        let style = Style::DEFAULT;
+        let tree = Tree::mk("AB", &[Tree::mk("C", &[]), Tree::mk("D", &[])]);
+
+        let text_width = |text: String| text.chars().count() as f32 * style.char_width;
+
+        let boxed = layout(
+            &tree,
+            &mut Index::default(),
+            &Options::default(),
+            &style,
+            &text_width,
+        );
+
+        let margin = 2.0 * style.margin();
+        let leaf = [
+            style.char_width + 2.0 * style.label_padding + margin,
+            style.label_height + margin,
+        ];
+        assert_eq!(boxed.kids[0].size, leaf);
+        assert_eq!(
+            boxed.size,
+            [
+                2.0 * leaf[0] + style.kid_spacing + margin,
+                style.label_height + style.label_spacing + leaf[1] + margin,
+            ]
+        );
+    }
+
+    #[test]
+    fn svg_to_svg_test1() {
+        let tree = Tree::mk("A", &[Tree::mk("<B>", &[Tree::mk("C", &[])])]);
+        let options = Options {
+            focus: Some(Index(vec![0])),
+            max_depth: Some(1),
+        };
+
+        let svg = to_svg(&tree, &options);
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("stroke=\"#ff0000\"").count(), 1);
+        assert!(svg.contains(">&lt;B&gt;</text>"));
+        assert!(svg.contains(">… 1 more</text>"));
+        assert!(!svg.contains(">C</text>"));
+    }
+
+    #[test]
+    fn svg_to_svg_test2() {
+        let mut tree = Tree::mk("A", &[Tree::mk("B", &[Tree::mk("C", &[])])]);
+        tree.kids[0].folded = true;
+
+        let svg = to_svg(&tree, &Options::default());
+
+        // the same placeholder as the boxes view stands in for folded kids
+        assert!(svg.contains(">B</text>"));
+        assert!(svg.contains(">… 1 hidden</text>"));
+        assert!(!svg.contains(">C</text>"));
+    }
+}
@@ -0,0 +1,162 @@
+//! Folding: hiding the kids of nodes in the views.
+
+use super::{Error, Index, Tree};
+
+/// The folded flags of some of the nodes of a tree: the index of each node,
+/// and whether it is folded.
+pub type Folds = Vec<(Index, bool)>;
+
+impl<L> Tree<L> {
+    /// Whether the node has kids that are hidden.
+    pub fn is_folded(&self) -> bool {
+        self.folded && !self.kids.is_empty()
+    }
+
+    /// The number of nodes that are hidden by folding this node.
+    pub fn hidden_count(&self) -> usize {
+        self.size() - 1
+    }
+
+    /// The flags that fold the nodes at `depth` below this one, and unfold the
+    /// nodes above them, so that exactly the nodes down to `depth` are shown.
+    /// Only the flags that change are listed.
+    pub fn folds_below_depth(&self, depth: usize) -> Folds {
+        fn go<L>(tree: &Tree<L>, depth: usize, index: &mut Index, folds: &mut Folds) {
+            if tree.folded != (depth == 0) {
+                folds.push((index.clone(), depth == 0));
+            }
+            if depth > 0 {
+                for (i, kid) in tree.kids.iter().enumerate() {
+                    index.push(i);
+                    go(kid, depth - 1, index, folds);
+                    index.pop();
+                }
+            }
+        }
+
+        let mut folds = vec![];
+        go(self, depth, &mut Index::default(), &mut folds);
+        folds
+    }
+
+    /// The flags that unfold every node. Only the flags that change are
+    /// listed.
+    pub fn folds_unfolding_all(&self) -> Folds {
+        fn go<L>(tree: &Tree<L>, index: &mut Index, folds: &mut Folds) {
+            if tree.folded {
+                folds.push((index.clone(), false));
+            }
+            for (i, kid) in tree.kids.iter().enumerate() {
+                index.push(i);
+                go(kid, index, folds);
+                index.pop();
+            }
+        }
+
+        let mut folds = vec![];
+        go(self, &mut Index::default(), &mut folds);
+        folds
+    }
+
+    /// The flags that unfold the ancestors of the node at `index`, so that it
+    /// is shown. Only the flags that change are listed.
+    pub fn folds_revealing(&self, index: &Index) -> Folds {
+        let mut folds = vec![];
+        let mut tree = self;
+        let mut ancestor = Index::default();
+        for step in index {
+            if tree.folded {
+                folds.push((ancestor.clone(), false));
+            }
+            let Some(kid) = tree.kids.get(*step) else {
+                break;
+            };
+            tree = kid;
+            ancestor.push(*step);
+        }
+        folds
+    }
+
+    /// Sets the flags of the nodes in `folds`, and returns the flags that they
+    /// had before. If any of the nodes isn't there, nothing is changed.
+    pub fn set_folds(&mut self, folds: Folds) -> Result<Folds, Error> {
+        for (index, _) in &folds {
+            self.at_index(index)?;
+        }
+        let mut old = Vec::with_capacity(folds.len());
+        for (index, folded) in folds {
+            let tree = self.at_index_mut(&index)?;
+            old.push((index, std::mem::replace(&mut tree.folded, folded)));
+        }
+        Ok(old)
+    }
+
+    /// The outermost folded node that the node at `index` is hidden inside of,
+    /// if any.
+    pub fn folded_ancestor(&self, index: &Index) -> Option<Index> {
+        let mut tree = self;
+        let mut ancestor = Index::default();
+        for step in index {
+            if tree.is_folded() {
+                return Some(ancestor);
+            }
+            tree = tree.kids.get(*step)?;
+            ancestor.push(*step);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Tooth;
+
+    #[test]
+    fn tree_fold_below_depth_test1() {
+        let mut tree = crate::tree::big_tree(2, 3);
+
+        let folds = tree.folds_below_depth(1);
+        assert_eq!(folds.len(), 2);
+        let old = tree.set_folds(folds).unwrap();
+        assert!(old.iter().all(|(_, folded)| !folded));
+        assert_eq!(tree.folds_below_depth(1), vec![]);
+
+        assert!(!tree.is_folded());
+        assert!(tree.kids.iter().all(Tree::is_folded));
+        assert_eq!(tree.kids[0].hidden_count(), 6);
+        assert_eq!(
+            tree.folded_ancestor(&Index(vec![1, 0, 1])),
+            Some(Index(vec![1]))
+        );
+        assert_eq!(tree.folded_ancestor(&Index(vec![1])), None);
+
+        let folds = tree.folds_revealing(&Index(vec![1, 0, 1]));
+        assert_eq!(folds, vec![(Index(vec![1]), false)]);
+        tree.set_folds(folds).unwrap();
+        assert_eq!(tree.folds_revealing(&Index(vec![1, 0, 1])), vec![]);
+        assert_eq!(tree.folded_ancestor(&Index(vec![1, 0, 1])), None);
+        assert!(tree.kids[0].is_folded());
+
+        tree.set_folds(tree.folds_unfolding_all()).unwrap();
+        assert_eq!(tree.folds_unfolding_all(), vec![]);
+        assert_eq!(tree, crate::tree::big_tree(2, 3));
+        assert!(tree.set_folds(vec![(Index(vec![5]), true)]).is_err());
+    }
+
+    #[test]
+    fn tree_fold_unwrap_test1() {
+        let mut tree = Tree::mk("A", &[Tree::mk("B", &[Tree::mk("C", &[])])]);
+        tree.folded = true;
+        tree.kids[0].folded = true;
+        let original = tree.clone();
+
+        // unwrapping goes through a zipper, which has to keep the flags of the
+        // nodes that it opens up
+        tree.wrap_with_path_at_index(&Index(vec![0, 0]), vec![Tooth::mk("X", &[], &[])]);
+        tree.unwrap_path_at_index(&Index(vec![0, 0]), &Index(vec![0, 0, 0]))
+            .unwrap();
+
+        assert_eq!(tree, original);
+    }
+}
@@ -0,0 +1,134 @@
+//! Holes: placeholder nodes that are yet to be filled in.
+//!
+//! A hole is a leaf whose label is [`HOLE_LABEL`], optionally followed by the
+//! name of the sort of tree that it stands for, as in `?Expr`. Keeping the sort
+//! in the label means that it survives being copied as an S-expression. Sort
+//! names are capitalized, so that other labels that start with `?`, such as
+//! `?x` or `??`, aren't holes.
+
+use super::{Error, Index, Tree};
+
+/// The label of a hole, and the start of the label of a typed hole.
+pub const HOLE_LABEL: &str = "?";
+
+/// Whether a leaf with `label` is a hole. This is for labels of other types
+/// than strings too, by the way that they are displayed.
+pub fn is_hole_label(label: &str) -> bool {
+    label
+        .strip_prefix(HOLE_LABEL)
+        .is_some_and(|sort| sort.is_empty() || is_sort_name(sort))
+}
+
+/// Whether `name` can be the sort of a typed hole: an ASCII capital letter
+/// followed by ASCII letters, digits and underscores.
+pub fn is_sort_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl Tree {
+    /// A placeholder node that is yet to be filled in.
+    pub fn hole() -> Self {
+        Self::mk(HOLE_LABEL, &[])
+    }
+
+    /// A placeholder for a tree of `sort`.
+    pub fn typed_hole(sort: &str) -> Self {
+        Self::mk(&format!("{HOLE_LABEL}{sort}"), &[])
+    }
+
+    pub fn is_hole(&self) -> bool {
+        is_hole_label(&self.label) && self.kids.is_empty()
+    }
+
+    /// The sort of the hole, if it is a typed hole.
+    pub fn hole_sort(&self) -> Option<&str> {
+        if !self.is_hole() {
+            return None;
+        }
+        Some(&self.label[HOLE_LABEL.len()..]).filter(|sort| !sort.is_empty())
+    }
+}
+
+impl Index {
+    /// Moves to the next hole in preorder, if there is one.
+    pub fn move_next_hole(&mut self, tree: &Tree) -> Result<(), Error> {
+        self.move_to_hole(tree, Self::move_next)
+    }
+
+    /// Moves to the previous hole in preorder, if there is one.
+    pub fn move_prev_hole(&mut self, tree: &Tree) -> Result<(), Error> {
+        self.move_to_hole(tree, Self::move_prev)
+    }
+
+    /// Takes steps until it gets to a hole. If it runs into the start or end
+    /// of the tree first, the index stays where it was, and the error is
+    /// [`Error::NoHole`] either way.
+    fn move_to_hole(
+        &mut self,
+        tree: &Tree,
+        step: impl Fn(&mut Self, &Tree) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut index = self.clone();
+        loop {
+            match step(&mut index, tree) {
+                Err(Error::AtRoot | Error::AtEnd) => return Err(Error::NoHole),
+                result => result?,
+            }
+            if tree.at_index(&index)?.is_hole() {
+                *self = index;
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::sexpr;
+
+    #[test]
+    fn tree_hole_test1() {
+        assert_eq!(Tree::hole().hole_sort(), None);
+        assert_eq!(Tree::typed_hole("Expr").hole_sort(), Some("Expr"));
+        assert!(Tree::typed_hole("Expr").is_hole());
+        assert!(!Tree::mk("?Expr", &[Tree::hole()]).is_hole());
+        assert_eq!(Tree::mk("x", &[]).hole_sort(), None);
+    }
+
+    #[test]
+    fn tree_hole_test2() {
+        assert!(is_hole_label("?"));
+        assert!(is_hole_label("?Expr_2"));
+        assert!(!is_hole_label("?x"));
+        assert!(!is_hole_label("??"));
+        assert!(!is_hole_label("?Expr!"));
+        assert!(!is_hole_label("x?"));
+        // a `?` operator with operands is a node like any other
+        assert!(!sexpr::parse("(? a b)").unwrap().is_hole());
+        assert_eq!(Tree::mk("?x", &[]).hole_sort(), None);
+    }
+
+    #[test]
+    fn index_move_next_hole_test1() {
+        let tree = sexpr::parse("(A ? (B ?Expr C) ?)").unwrap();
+        let mut index = Index(vec![]);
+
+        index.move_next_hole(&tree).unwrap();
+        assert_eq!(index, Index(vec![0]));
+        index.move_next_hole(&tree).unwrap();
+        assert_eq!(index, Index(vec![1, 0]));
+        index.move_next_hole(&tree).unwrap();
+        assert_eq!(index, Index(vec![2]));
+        assert_eq!(index.move_next_hole(&tree), Err(Error::NoHole));
+        assert_eq!(index, Index(vec![2]));
+
+        index.move_prev_hole(&tree).unwrap();
+        assert_eq!(index, Index(vec![1, 0]));
+        index.move_prev_hole(&tree).unwrap();
+        assert_eq!(index.move_prev_hole(&tree), Err(Error::NoHole));
+        assert_eq!(index, Index(vec![0]));
+    }
+}
@@ -0,0 +1,234 @@
+//! Stable identifiers for the nodes of a [`Tree`].
+//!
+//! An [`Index`] says where a node is, so it changes whenever an edit inserts or
+//! removes a node before it. A [`NodeId`] says which node it is, and stays with
+//! the node through edits and serialization. [`NodeIds`] finds the current
+//! index of a node from its id.
+
+use super::{Index, Tree};
+use std::collections::HashMap;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+)]
+#[serde(transparent)]
+pub struct NodeId(u64);
+
+impl std::fmt::Display for NodeId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl<L> Tree<L> {
+    /// Takes the ids off of this node and the nodes below it, so that they are
+    /// given fresh ids when they are recorded, as new nodes are.
+    pub fn clear_ids(&mut self) {
+        self.id = None;
+        for kid in &mut self.kids {
+            kid.clear_ids();
+        }
+    }
+}
+
+/// Where each node of a tree is, by its id.
+///
+/// Edits keep it up to date by calling [`Self::forget`] on the subtree that
+/// they change before they change it, and [`Self::record`] on it after.
+#[derive(Debug, Clone, Default)]
+pub struct NodeIds {
+    indices: HashMap<NodeId, Index>,
+    /// Greater than every id that was recorded, so that fresh ids don't
+    /// collide with the ids that nodes were loaded or pasted with.
+    next: u64,
+}
+
+impl NodeIds {
+    /// Gives every node of `tree` an id, and records where they are.
+    pub fn new<L>(tree: &mut Tree<L>) -> Self {
+        let mut ids = Self::default();
+        ids.record(tree, &Index::default());
+        ids
+    }
+
+    /// The current index of the node with `id`, if it is in the tree.
+    pub fn index_of(&self, id: NodeId) -> Option<&Index> {
+        self.indices.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Forgets the nodes of the subtree of `tree` at `scope`.
+    pub fn forget<L>(&mut self, tree: &Tree<L>, scope: &Index) {
+        fn go<L>(tree: &Tree<L>, indices: &mut HashMap<NodeId, Index>) {
+            if let Some(id) = tree.id {
+                indices.remove(&id);
+            }
+            for kid in &tree.kids {
+                go(kid, indices);
+            }
+        }
+
+        if let Ok(subtree) = tree.at_index(scope) {
+            go(subtree, &mut self.indices);
+        }
+    }
+
+    /// Records where the nodes of the subtree of `tree` at `scope` are. New
+    /// nodes, which have no id yet, are given fresh ids. So are nodes with the
+    /// id of a node that was already recorded, which only a file that was
+    /// edited by hand can have, as copies are made without ids.
+    pub fn record<L>(&mut self, tree: &mut Tree<L>, scope: &Index) {
+        fn go<L>(tree: &mut Tree<L>, index: &mut Index, ids: &mut NodeIds) {
+            let id = match tree.id {
+                Some(id) if !ids.indices.contains_key(&id) => {
+                    ids.next = ids.next.max(id.0.saturating_add(1));
+                    id
+                }
+                _ => {
+                    let id = NodeId(ids.next);
+                    ids.next += 1;
+                    tree.id = Some(id);
+                    id
+                }
+            };
+            ids.indices.insert(id, index.clone());
+            for (i, kid) in tree.kids.iter_mut().enumerate() {
+                index.push(i);
+                go(kid, index, ids);
+                index.pop();
+            }
+        }
+
+        if let Ok(subtree) = tree.at_index_mut(scope) {
+            go(subtree, &mut scope.clone(), self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{Edit, History};
+
+    /// Applies `edit` the way the app does, keeping `ids` up to date.
+    fn apply(edit: Edit, tree: &mut Tree, ids: &mut NodeIds) {
+        let scope = edit.scope();
+        ids.forget(tree, &scope);
+        History::default()
+            .apply(edit, tree, &mut Index::default())
+            .unwrap();
+        ids.record(tree, &scope);
+    }
+
+    #[test]
+    fn node_ids_test1() {
+        let mut tree = Tree::mk(
+            "A",
+            &[Tree::mk("B", &[Tree::mk("C", &[])]), Tree::mk("D", &[])],
+        );
+        let mut ids = NodeIds::new(&mut tree);
+        let c = tree.kids[0].kids[0].id.unwrap();
+        let d = tree.kids[1].id.unwrap();
+        assert_eq!(ids.len(), 4);
+
+        apply(
+            Edit::InsertKid {
+                parent: Index(vec![]),
+                position: 0,
+                kid: Tree::mk("X", &[]),
+            },
+            &mut tree,
+            &mut ids,
+        );
+        assert_eq!(ids.index_of(d), Some(&Index(vec![2])));
+        assert_eq!(ids.index_of(c), Some(&Index(vec![1, 0])));
+        assert_eq!(ids.len(), 5);
+
+        apply(
+            Edit::Move {
+                from: Index(vec![1, 0]),
+                parent: Index(vec![]),
+                position: 0,
+            },
+            &mut tree,
+            &mut ids,
+        );
+        assert_eq!(tree.kids[0].id, Some(c));
+        assert_eq!(ids.index_of(c), Some(&Index(vec![0])));
+
+        // the copy gets an id of its own
+        apply(
+            Edit::Duplicate {
+                index: Index(vec![3]),
+            },
+            &mut tree,
+            &mut ids,
+        );
+        assert_eq!(tree.kids[3].id, Some(d));
+        assert_ne!(tree.kids[4].id, Some(d));
+        assert_eq!(ids.len(), 6);
+    }
+
+    #[test]
+    fn node_ids_duplicate_test1() {
+        let mut tree = Tree::mk("A", &[Tree::mk("B", &[Tree::mk("C", &[])])]);
+        let mut ids = NodeIds::new(&mut tree);
+        let b = tree.kids[0].id.unwrap();
+        let c = tree.kids[0].kids[0].id.unwrap();
+
+        // the copy is made without ids, so it doesn't matter where it lands
+        let mut copy = tree.clone();
+        copy.duplicate_at_index(&Index(vec![0]), &mut Index::default())
+            .unwrap();
+        assert_eq!(copy.kids[1].id, None);
+        assert_eq!(copy.kids[1].kids[0].id, None);
+
+        apply(
+            Edit::Duplicate {
+                index: Index(vec![0]),
+            },
+            &mut tree,
+            &mut ids,
+        );
+        let copy = tree.kids[1].id.unwrap();
+        apply(
+            Edit::SwapSiblings {
+                index: Index(vec![1]),
+                other: 0,
+            },
+            &mut tree,
+            &mut ids,
+        );
+
+        // with the copy on the left, the original keeps its ids
+        assert_eq!(tree.kids[0].id, Some(copy));
+        assert_eq!(tree.kids[1].id, Some(b));
+        assert_eq!(tree.kids[1].kids[0].id, Some(c));
+        assert_eq!(ids.index_of(b), Some(&Index(vec![1])));
+        assert_eq!(ids.index_of(c), Some(&Index(vec![1, 0])));
+        assert_ne!(tree.kids[0].kids[0].id, Some(c));
+        assert_eq!(ids.len(), 5);
+    }
+
+    #[test]
+    fn node_ids_record_test1() {
+        let mut tree: Tree = serde_json::from_str(
+            r#"{"label":"A","kids":[{"label":"B","kids":[],"id":1000},{"label":"C","kids":[]}]}"#,
+        )
+        .unwrap();
+        let ids = NodeIds::new(&mut tree);
+
+        // the loaded id is kept, and the fresh ones after it come after it
+        assert_eq!(tree.kids[0].id, Some(NodeId(1000)));
+        assert_eq!(ids.index_of(NodeId(1000)), Some(&Index(vec![0])));
+        assert_eq!(tree.kids[1].id, Some(NodeId(1001)));
+        assert_eq!(ids.len(), 3);
+    }
+}
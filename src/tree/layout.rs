@@ -0,0 +1,471 @@
+//! The tidy layout of a [`Tree`] as a top-down node-link diagram, after
+//! Reingold and Tilford.
+//!
+//! Every subtree is laid out on its own first, and is summed up by its
+//! contour: the left and right edges of its nodes at each depth. Siblings are
+//! then pushed as close together as their contours allow, and each parent is
+//! centered over its first and last kid. So a subtree always has the same
+//! shape wherever it is in the tree, and the diagram grows with the number of
+//! leaves rather than with the nesting of boxes.
+//!
+//! The kids of folded nodes are left out.
+//!
+//! The shapes of the subtrees can be kept in a [`ShapeCache`] between
+//! layouts, so that after an edit only the subtree that it changed and the
+//! ancestors of that subtree are laid out again. The nodes are then placed
+//! from the shapes, which takes no more than a walk over them.
+
+use super::{Index, Tree};
+use std::collections::HashMap;
+
+/// The space between nodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spacing {
+    /// The height of every node.
+    pub node_height: f32,
+    /// The smallest horizontal space between two nodes at the same depth.
+    pub sibling: f32,
+    /// The vertical space between two depths.
+    pub level: f32,
+}
+
+/// A node placed in a [`Layout`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub index: Index,
+    /// The position of the parent in [`Layout::nodes`], if any.
+    pub parent: Option<usize>,
+    /// The top left corner.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+}
+
+impl Node {
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        (0..2).all(|i| self.position[i] <= point[i] && point[i] <= self.position[i] + self.size[i])
+    }
+
+    /// The middle of the top edge, where the edge from the parent ends.
+    pub fn top(&self) -> [f32; 2] {
+        [self.position[0] + self.size[0] / 2.0, self.position[1]]
+    }
+
+    /// The middle of the bottom edge, where the edges to the kids start.
+    pub fn bottom(&self) -> [f32; 2] {
+        [
+            self.position[0] + self.size[0] / 2.0,
+            self.position[1] + self.size[1],
+        ]
+    }
+}
+
+/// The positions of all of the nodes of a tree.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layout {
+    /// The nodes in preorder, so a parent always comes before its kids.
+    pub nodes: Vec<Node>,
+    /// The size of the whole diagram, which starts at `[0, 0]`.
+    pub size: [f32; 2],
+    /// The positions in `nodes` of the nodes by their indices.
+    positions: HashMap<Index, usize>,
+    /// The positions in `nodes` of the nodes at each depth, from left to
+    /// right.
+    rows: Vec<Vec<usize>>,
+    /// The distance from the top of one depth to the top of the next.
+    row_height: f32,
+    node_height: f32,
+}
+
+/// A subtree laid out on its own, with its root centered at 0.
+#[derive(Debug, Default)]
+struct Shape {
+    width: f32,
+    /// The centers of the kids.
+    kid_offsets: Vec<f32>,
+    kids: Vec<Shape>,
+    /// The left and right edges of the nodes at each depth, starting with the
+    /// root, or nothing if the subtree has to be laid out again.
+    contour: Vec<[f32; 2]>,
+}
+
+impl Shape {
+    /// Lays out the subtree `tree` again, if it has to be, using the shapes
+    /// of its kids that are still laid out.
+    fn update<L>(&mut self, tree: &Tree<L>, width: &impl Fn(&Tree<L>) -> f32, spacing: &Spacing) {
+        if !self.contour.is_empty() {
+            return;
+        }
+        let kid_count = if tree.is_folded() { 0 } else { tree.kids.len() };
+        if self.kids.len() != kid_count {
+            self.kids.clear();
+            self.kids.resize_with(kid_count, Self::default);
+        }
+        for (kid, tree) in self.kids.iter_mut().zip(&tree.kids) {
+            kid.update(tree, width, spacing);
+        }
+
+        // place the kids from left to right, relative to the first one
+        let mut kid_offsets = Vec::with_capacity(self.kids.len());
+        let mut row_contour: Vec<[f32; 2]> = vec![];
+        for kid in &self.kids {
+            let offset = if kid_offsets.is_empty() {
+                0.0
+            } else {
+                row_contour
+                    .iter()
+                    .zip(&kid.contour)
+                    .map(|(placed, edges)| placed[1] + spacing.sibling - edges[0])
+                    .fold(f32::NEG_INFINITY, f32::max)
+            };
+            for (depth, [left, right]) in kid.contour.iter().enumerate() {
+                match row_contour.get_mut(depth) {
+                    // the new kid is to the right of everything placed so far
+                    Some(edges) => edges[1] = right + offset,
+                    None => row_contour.push([left + offset, right + offset]),
+                }
+            }
+            kid_offsets.push(offset);
+        }
+
+        let center = match (kid_offsets.first(), kid_offsets.last()) {
+            (Some(first), Some(last)) => (first + last) / 2.0,
+            _ => 0.0,
+        };
+        for offset in &mut kid_offsets {
+            *offset -= center;
+        }
+        let width = width(tree);
+        let mut contour = vec![[-width / 2.0, width / 2.0]];
+        contour.extend(
+            row_contour
+                .iter()
+                .map(|[left, right]| [left - center, right - center]),
+        );
+
+        self.width = width;
+        self.kid_offsets = kid_offsets;
+        self.contour = contour;
+    }
+}
+
+/// The shapes of the subtrees of a tree, kept between layouts. It mirrors the
+/// tree, and a subtree is laid out again only if its shape was forgotten.
+#[derive(Debug, Default)]
+pub struct ShapeCache {
+    root: Shape,
+}
+
+impl ShapeCache {
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Forgets the shapes of the subtree at `index` and of its ancestors.
+    pub fn invalidate(&mut self, index: &Index) {
+        let mut shape = &mut self.root;
+        for step in index {
+            shape.contour.clear();
+            let Some(kid) = shape.kids.get_mut(*step) else {
+                return;
+            };
+            shape = kid;
+        }
+        *shape = Shape::default();
+    }
+}
+
+impl Layout {
+    /// Lays out `tree` with nodes as wide as `width` says.
+    pub fn tidy<L>(tree: &Tree<L>, width: impl Fn(&Tree<L>) -> f32, spacing: &Spacing) -> Self {
+        Self::tidy_cached(tree, width, spacing, &mut ShapeCache::default())
+    }
+
+    /// Lays out `tree` like [`Self::tidy`], with the shapes of the subtrees
+    /// that are still in `cache`.
+    pub fn tidy_cached<L>(
+        tree: &Tree<L>,
+        width: impl Fn(&Tree<L>) -> f32,
+        spacing: &Spacing,
+        cache: &mut ShapeCache,
+    ) -> Self {
+        cache.root.update(tree, &width, spacing);
+        let shape = &cache.root;
+        let left = shape
+            .contour
+            .iter()
+            .map(|edges| edges[0])
+            .fold(0.0, f32::min);
+        let right = shape
+            .contour
+            .iter()
+            .map(|edges| edges[1])
+            .fold(0.0, f32::max);
+        let depths = shape.contour.len() as f32;
+
+        let mut layout = Self {
+            nodes: vec![],
+            size: [
+                right - left,
+                depths * spacing.node_height + (depths - 1.0) * spacing.level,
+            ],
+            positions: HashMap::new(),
+            rows: vec![],
+            row_height: spacing.node_height + spacing.level,
+            node_height: spacing.node_height,
+        };
+        layout.place(shape, -left, &mut Index::default(), None, spacing);
+        layout
+    }
+
+    fn place(
+        &mut self,
+        shape: &Shape,
+        center: f32,
+        index: &mut Index,
+        parent: Option<usize>,
+        spacing: &Spacing,
+    ) {
+        let id = self.nodes.len();
+        self.positions.insert(index.clone(), id);
+        // the nodes are placed in preorder, so those at a depth from left to
+        // right
+        if self.rows.len() <= index.len() {
+            self.rows.resize_with(index.len() + 1, Vec::new);
+        }
+        self.rows[index.len()].push(id);
+        self.nodes.push(Node {
+            index: index.clone(),
+            parent,
+            position: [
+                center - shape.width / 2.0,
+                index.len() as f32 * (spacing.node_height + spacing.level),
+            ],
+            size: [shape.width, spacing.node_height],
+        });
+        for (i, (kid, offset)) in shape.kids.iter().zip(&shape.kid_offsets).enumerate() {
+            index.push(i);
+            self.place(kid, center + offset, index, Some(id), spacing);
+            index.pop();
+        }
+    }
+
+    /// The node at `index`, if any.
+    pub fn get(&self, index: &Index) -> Option<&Node> {
+        self.positions.get(index).map(|&id| &self.nodes[id])
+    }
+
+    /// The nodes that overlap the rectangle from `min` to `max`.
+    pub fn nodes_in(&self, min: [f32; 2], max: [f32; 2]) -> impl Iterator<Item = &Node> {
+        self.rows_in(min[1], max[1], 0.0)
+            .iter()
+            .flat_map(move |row| {
+                // the nodes of a row don't overlap, so their right edges are in
+                // order as well
+                let start = row.partition_point(|&id| {
+                    let node = &self.nodes[id];
+                    node.position[0] + node.size[0] < min[0]
+                });
+                let end = row.partition_point(|&id| self.nodes[id].position[0] <= max[0]);
+                row[start..end.max(start)].iter().map(|&id| &self.nodes[id])
+            })
+    }
+
+    /// The edges that may cross the rectangle from `min` to `max`, each as
+    /// the parent and the kid that it joins.
+    pub fn edges_in(&self, min: [f32; 2], max: [f32; 2]) -> impl Iterator<Item = (&Node, &Node)> {
+        let level = self.row_height - self.node_height;
+        self.rows_in(min[1], max[1], level)
+            .iter()
+            .flat_map(move |row| {
+                // the parents of the nodes of a row are in the same order as the
+                // nodes, so the edges don't cross, and the left and right ends
+                // of the edges are in order
+                let ends = |id: usize| {
+                    let kid = &self.nodes[id];
+                    let parent = kid.parent.map_or(kid, |parent| &self.nodes[parent]);
+                    let [from, to] = [parent.bottom()[0], kid.top()[0]];
+                    [from.min(to), from.max(to)]
+                };
+                let start = row.partition_point(|&id| ends(id)[1] < min[0]);
+                let end = row.partition_point(|&id| ends(id)[0] <= max[0]);
+                row[start..end.max(start)].iter().filter_map(|&id| {
+                    let kid = &self.nodes[id];
+                    Some((&self.nodes[kid.parent?], kid))
+                })
+            })
+    }
+
+    /// The rows whose nodes, together with the space of `above` over them,
+    /// overlap the heights from `min_y` to `max_y`.
+    fn rows_in(&self, min_y: f32, max_y: f32, above: f32) -> &[Vec<usize>] {
+        let first = ((min_y - self.node_height) / self.row_height)
+            .ceil()
+            .max(0.0) as usize;
+        let end = ((max_y + above) / self.row_height).floor().max(-1.0) + 1.0;
+        let end = (end as usize).min(self.rows.len());
+        &self.rows[first.min(end)..end]
+    }
+
+    /// The node that `point` is on, if any.
+    pub fn node_at(&self, point: [f32; 2]) -> Option<&Node> {
+        if point[1] < 0.0 {
+            return None;
+        }
+        let row = self.rows.get((point[1] / self.row_height) as usize)?;
+        // the last node that starts left of `point` is the only one that can
+        // contain it
+        let after = row.partition_point(|&id| self.nodes[id].position[0] <= point[0]);
+        let node = &self.nodes[row[after.checked_sub(1)?]];
+        node.contains(point).then_some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACING: Spacing = Spacing {
+        node_height: 20.0,
+        sibling: 10.0,
+        level: 30.0,
+    };
+
+    fn width(tree: &Tree) -> f32 {
+        10.0 * tree.label.len() as f32
+    }
+
+    #[test]
+    fn layout_tidy_test1() {
+        let tree = Tree::mk("AAA", &[Tree::mk("B", &[]), Tree::mk("C", &[])]);
+
+        let layout = Layout::tidy(&tree, width, &SPACING);
+
+        assert_eq!(layout.size, [30.0, 70.0]);
+        let positions: Vec<_> = layout.nodes.iter().map(|node| node.position).collect();
+        assert_eq!(positions, [[0.0, 0.0], [0.0, 50.0], [20.0, 50.0]]);
+        assert_eq!(layout.nodes[2].parent, Some(0));
+        assert_eq!(layout.nodes[2].index, Index(vec![1]));
+    }
+
+    #[test]
+    fn layout_tidy_test2() {
+        // the leaf "C" fits under the wide kid of "B", so it is pushed only
+        // as far as the contour of "B" needs
+        let tree = Tree::mk(
+            "A",
+            &[Tree::mk("B", &[Tree::mk("DDDDD", &[])]), Tree::mk("C", &[])],
+        );
+
+        let layout = Layout::tidy(&tree, width, &SPACING);
+
+        let c = layout.get(&Index(vec![1])).unwrap();
+        let b = layout.get(&Index(vec![0])).unwrap();
+        assert_eq!(c.position[0] - (b.position[0] + b.size[0]), SPACING.sibling);
+        assert_eq!(layout.node_at(c.top()), Some(c));
+        assert_eq!(layout.node_at([-1.0, -1.0]), None);
+    }
+
+    #[test]
+    fn layout_tidy_test3() {
+        let tree = crate::tree::big_tree(3, 3);
+
+        let layout = Layout::tidy(&tree, width, &SPACING);
+
+        // nodes at the same depth don't overlap, and parents are above kids
+        let mut rows: Vec<Vec<&Node>> = vec![];
+        for node in &layout.nodes {
+            if rows.len() <= node.index.len() {
+                rows.push(vec![]);
+            }
+            rows[node.index.len()].push(node);
+            if let Some(parent) = node.parent {
+                assert!(layout.nodes[parent].bottom()[1] < node.top()[1]);
+            }
+        }
+        for row in rows {
+            for pair in row.windows(2) {
+                let right = pair[0].position[0] + pair[0].size[0];
+                assert!(right + SPACING.sibling <= pair[1].position[0] + 1e-3);
+            }
+        }
+        assert!(layout.nodes.iter().all(|node| node.position[0] >= 0.0));
+    }
+
+    #[test]
+    fn layout_node_at_test1() {
+        let layout = Layout::tidy(&crate::tree::big_tree(3, 3), width, &SPACING);
+
+        for node in &layout.nodes {
+            let center = [
+                node.position[0] + node.size[0] / 2.0,
+                node.position[1] + node.size[1] / 2.0,
+            ];
+            assert_eq!(layout.node_at(center), Some(node));
+            assert_eq!(layout.get(&node.index), Some(node));
+            // between the depths
+            let below = [center[0], node.position[1] + node.size[1] + 1.0];
+            assert_eq!(layout.node_at(below), None);
+        }
+        assert_eq!(layout.node_at([layout.size[0] + 1.0, 1.0]), None);
+    }
+
+    #[test]
+    fn layout_shape_cache_test1() {
+        let mut tree = crate::tree::big_tree(3, 3);
+        let mut cache = ShapeCache::default();
+        Layout::tidy_cached(&tree, width, &SPACING, &mut cache);
+
+        // a stale cache keeps the old shapes, until the edited subtree is
+        // invalidated
+        tree.kids[1].kids[0].label = "a long label".to_owned();
+        let stale = Layout::tidy_cached(&tree, width, &SPACING, &mut cache);
+        assert_ne!(stale, Layout::tidy(&tree, width, &SPACING));
+        cache.invalidate(&Index(vec![1, 0]));
+        assert!(cache.root.kids[0].contour.len() > 1);
+        let layout = Layout::tidy_cached(&tree, width, &SPACING, &mut cache);
+        assert_eq!(layout, Layout::tidy(&tree, width, &SPACING));
+
+        tree.kids[2].folded = true;
+        cache.invalidate(&Index(vec![2]));
+        let layout = Layout::tidy_cached(&tree, width, &SPACING, &mut cache);
+        assert_eq!(layout, Layout::tidy(&tree, width, &SPACING));
+    }
+
+    #[test]
+    fn layout_nodes_in_test1() {
+        let layout = Layout::tidy(&crate::tree::big_tree(4, 3), width, &SPACING);
+        let overlaps = |[min, max]: [[f32; 2]; 2], from: [f32; 2], to: [f32; 2]| {
+            (0..2).all(|i| min[i] <= from[i].max(to[i]) && from[i].min(to[i]) <= max[i])
+        };
+
+        for rect in [
+            [[0.0, 0.0], layout.size],
+            [[40.0, 45.0], [90.0, 120.0]],
+            [[-10.0, 60.0], [5.0, 70.0]],
+            [[layout.size[0] / 2.0, 10.0]; 2],
+        ] {
+            let nodes: Vec<_> = layout.nodes_in(rect[0], rect[1]).collect();
+            let expected: Vec<_> = layout
+                .nodes
+                .iter()
+                .filter(|node| {
+                    let max = [0, 1].map(|i| node.position[i] + node.size[i]);
+                    overlaps(rect, node.position, max)
+                })
+                .collect();
+            assert_eq!(nodes.len(), expected.len());
+            assert!(expected.iter().all(|node| nodes.contains(node)));
+
+            // every edge whose bounding box overlaps the rect is there
+            let edges: Vec<_> = layout.edges_in(rect[0], rect[1]).collect();
+            for kid in &layout.nodes {
+                let Some(parent) = kid.parent.map(|parent| &layout.nodes[parent]) else {
+                    continue;
+                };
+                if overlaps(rect, parent.bottom(), kid.top()) {
+                    assert!(edges.contains(&(parent, kid)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,452 @@
+//! Reading and writing a [`Tree`] as an S-expression.
+//!
+//! A leaf is written as its label, and any other node as a list of its label
+//! followed by its kids, as in `(A (B C) D)`. A leaf may also be written as a
+//! list of just its label, as in `(A)`. Labels that aren't plain atoms are
+//! written as strings, as in `("a label" "(")`, where `\"`, `\\`, `\n` and
+//! `\t` are escapes. The attributes of a node follow its label in braces, as
+//! keys and values in turn, as in `(A {color red note "a b"} B)`, so a leaf
+//! with attributes is written as a list. A `;` starts a comment that lasts
+//! until the end of the line.
+
+use super::{Attrs, Tree};
+
+/// The line width that [`to_string`] fits its output into.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// How far kids that don't fit on the line of their parent are indented.
+const INDENT: usize = 2;
+
+/// A position in the parsed text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// The offset in bytes.
+    pub offset: usize,
+    /// The line, starting from 1.
+    pub line: usize,
+    /// The column in characters, starting from 1.
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The text ended where a tree was expected.
+    UnexpectedEnd,
+    /// There is a `)` without a matching `(`.
+    UnexpectedClose,
+    /// A list has to start with the label of the node, not with a list.
+    ExpectedLabel,
+    /// Braces can only follow the label at the start of a list.
+    MisplacedAttrs,
+    /// The attributes have to be keys and values in turn.
+    ExpectedAttr,
+    /// The `(` at the position is never closed.
+    UnclosedList,
+    /// The `{` at the position is never closed.
+    UnclosedAttrs,
+    /// The string at the position is never closed.
+    UnclosedString,
+    InvalidEscape(char),
+    /// There is more text after the tree.
+    TrailingInput,
+}
+
+/// Why parsing failed, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub position: Position,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Position { line, column, .. } = self.position;
+        write!(f, "{line}:{column}: ")?;
+        match &self.kind {
+            ErrorKind::UnexpectedEnd => write!(f, "expected a tree, but the text ended"),
+            ErrorKind::UnexpectedClose => write!(f, "unexpected `)`"),
+            ErrorKind::ExpectedLabel => write!(f, "expected a label at the start of the list"),
+            ErrorKind::MisplacedAttrs => write!(f, "attributes must follow the label of a list"),
+            ErrorKind::ExpectedAttr => write!(f, "expected a key and a value, or `}}`"),
+            ErrorKind::UnclosedList => write!(f, "this `(` is never closed"),
+            ErrorKind::UnclosedAttrs => write!(f, "this `{{` is never closed"),
+            ErrorKind::UnclosedString => write!(f, "this string is never closed"),
+            ErrorKind::InvalidEscape(c) => write!(f, "invalid escape `\\{c}`"),
+            ErrorKind::TrailingInput => write!(f, "unexpected text after the tree"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parses `text` as a single tree.
+pub fn parse(text: &str) -> Result<Tree, Error> {
+    let mut parser = Parser {
+        text,
+        position: Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        },
+    };
+    let tree = parser.tree()?;
+    parser.skip_trivia();
+    if parser.peek().is_some() {
+        return Err(parser.error(ErrorKind::TrailingInput));
+    }
+    Ok(tree)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: Position,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position.offset..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position.offset += c.len_utf8();
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error {
+            kind,
+            position: self.position,
+        }
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ';' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn tree(&mut self) -> Result<Tree, Error> {
+        self.skip_trivia();
+        match self.peek() {
+            None => Err(self.error(ErrorKind::UnexpectedEnd)),
+            Some(')') => Err(self.error(ErrorKind::UnexpectedClose)),
+            Some('{' | '}') => Err(self.error(ErrorKind::MisplacedAttrs)),
+            Some('(') => self.list(),
+            Some(_) => Ok(Tree::mk(&self.label()?, &[])),
+        }
+    }
+
+    fn list(&mut self) -> Result<Tree, Error> {
+        let open = self.error(ErrorKind::UnclosedList);
+        self.bump();
+        self.skip_trivia();
+        let label = match self.peek() {
+            None => return Err(open),
+            Some('(' | ')' | '{' | '}') => return Err(self.error(ErrorKind::ExpectedLabel)),
+            Some(_) => self.label()?,
+        };
+        self.skip_trivia();
+        let attrs = if self.peek() == Some('{') {
+            self.attrs()?
+        } else {
+            Attrs::new()
+        };
+        let mut kids = vec![];
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => return Err(open),
+                Some(')') => {
+                    self.bump();
+                    let mut tree = Tree::new(label, kids);
+                    tree.attrs = attrs;
+                    return Ok(tree);
+                }
+                Some(_) => kids.push(self.tree()?),
+            }
+        }
+    }
+
+    /// The keys and values between braces.
+    fn attrs(&mut self) -> Result<Attrs, Error> {
+        let open = self.error(ErrorKind::UnclosedAttrs);
+        self.bump();
+        let mut attrs = Attrs::new();
+        loop {
+            self.skip_trivia();
+            let key = match self.peek() {
+                None => return Err(open),
+                Some('}') => {
+                    self.bump();
+                    return Ok(attrs);
+                }
+                Some('(' | ')' | '{') => return Err(self.error(ErrorKind::ExpectedAttr)),
+                Some(_) => self.label()?,
+            };
+            self.skip_trivia();
+            let value = match self.peek() {
+                None => return Err(open),
+                Some('(' | ')' | '{' | '}') => return Err(self.error(ErrorKind::ExpectedAttr)),
+                Some(_) => self.label()?,
+            };
+            attrs.insert(key, value);
+        }
+    }
+
+    fn label(&mut self) -> Result<String, Error> {
+        if self.peek() == Some('"') {
+            return self.string();
+        }
+        let start = self.position.offset;
+        while self.peek().is_some_and(|c| !is_delimiter(c)) {
+            self.bump();
+        }
+        Ok(self.text[start..self.position.offset].to_owned())
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let open = self.error(ErrorKind::UnclosedString);
+        self.bump();
+        let mut string = String::new();
+        loop {
+            let position = self.position;
+            match self.bump() {
+                None => return Err(open),
+                Some('"') => return Ok(string),
+                Some('\\') => match self.bump() {
+                    None => return Err(open),
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('n') => string.push('\n'),
+                    Some('t') => string.push('\t'),
+                    Some(c) => {
+                        return Err(Error {
+                            kind: ErrorKind::InvalidEscape(c),
+                            position,
+                        });
+                    }
+                },
+                Some(c) => string.push(c),
+            }
+        }
+    }
+}
+
+/// Whether `c` ends an unquoted label.
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '{' | '}' | '"' | ';')
+}
+
+/// Prints `tree`, breaking lines to fit into [`DEFAULT_WIDTH`] columns where
+/// possible.
+pub fn to_string(tree: &Tree) -> String {
+    to_string_with_width(tree, DEFAULT_WIDTH)
+}
+
+/// Prints `tree`, breaking lines to fit into `width` columns where possible.
+///
+/// A list that fits on the rest of its line is printed there, and otherwise
+/// each of its kids goes on a line of its own. A single label that is longer
+/// than the line still overflows it.
+pub fn to_string_with_width(tree: &Tree, width: usize) -> String {
+    let mut string = String::new();
+    write_tree(&mut string, tree, 0, 0, width);
+    string
+}
+
+/// Writes `tree` starting at column `indent`, followed by `trailing` closing
+/// parentheses that are written by its ancestors.
+fn write_tree(string: &mut String, tree: &Tree, indent: usize, trailing: usize, width: usize) {
+    let limit = width.saturating_sub(indent + trailing);
+    if tree.kids.is_empty() || flat_width(tree, limit).is_some() {
+        write_flat(string, tree);
+        return;
+    }
+    string.push('(');
+    string.push_str(&quote(&tree.label));
+    string.push_str(&attrs_to_string(&tree.attrs));
+    let indent = indent + INDENT;
+    for (i, kid) in tree.kids.iter().enumerate() {
+        string.push('\n');
+        string.extend(std::iter::repeat_n(' ', indent));
+        let is_last = i + 1 == tree.kids.len();
+        let trailing = if is_last { trailing + 1 } else { 0 };
+        write_tree(string, kid, indent, trailing, width);
+    }
+    string.push(')');
+}
+
+fn write_flat(string: &mut String, tree: &Tree) {
+    if tree.kids.is_empty() && tree.attrs.is_empty() {
+        string.push_str(&quote(&tree.label));
+        return;
+    }
+    string.push('(');
+    string.push_str(&quote(&tree.label));
+    string.push_str(&attrs_to_string(&tree.attrs));
+    for kid in &tree.kids {
+        string.push(' ');
+        write_flat(string, kid);
+    }
+    string.push(')');
+}
+
+/// The width of `tree` printed on one line, if it is at most `limit`.
+fn flat_width(tree: &Tree, limit: usize) -> Option<usize> {
+    let mut width = quote(&tree.label).chars().count();
+    if !tree.kids.is_empty() || !tree.attrs.is_empty() {
+        width += 2 + attrs_to_string(&tree.attrs).chars().count();
+        for kid in &tree.kids {
+            width += 1 + flat_width(kid, limit.checked_sub(width + 1)?)?;
+        }
+    }
+    (width <= limit).then_some(width)
+}
+
+/// The attributes as they follow a label, with a space before them, or
+/// nothing if there are none.
+fn attrs_to_string(attrs: &Attrs) -> String {
+    if attrs.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = attrs
+        .iter()
+        .map(|(key, value)| format!("{} {}", quote(key), quote(value)))
+        .collect();
+    format!(" {{{}}}", pairs.join(" "))
+}
+
+/// `label` as an atom if it is one, and otherwise as a string.
+fn quote(label: &str) -> std::borrow::Cow<'_, str> {
+    if !label.is_empty() && !label.chars().any(is_delimiter) {
+        return label.into();
+    }
+    let mut string = String::from('"');
+    for c in label.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            '\n' => string.push_str("\\n"),
+            '\t' => string.push_str("\\t"),
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    string.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::example;
+
+    #[test]
+    fn sexpr_parse_test1() {
+        assert_eq!(parse("(A (B D E) (C))").unwrap(), example());
+        assert_eq!(parse("A").unwrap(), Tree::mk("A", &[]));
+    }
+
+    #[test]
+    fn sexpr_parse_test2() {
+        let text = r#"
+            ; a comment
+            ("a b" ; another comment
+              ("(" "\"\\\n")
+              "")
+        "#;
+
+        assert_eq!(
+            parse(text).unwrap(),
+            Tree::mk(
+                "a b",
+                &[Tree::mk("(", &[Tree::mk("\"\\\n", &[])]), Tree::mk("", &[])]
+            )
+        );
+    }
+
+    #[test]
+    fn sexpr_parse_error_test1() {
+        let cases = [
+            ("", ErrorKind::UnexpectedEnd, 1, 1),
+            ("(A\n  (B)", ErrorKind::UnclosedList, 1, 1),
+            ("(A (B)) )", ErrorKind::TrailingInput, 1, 9),
+            (")", ErrorKind::UnexpectedClose, 1, 1),
+            ("(A\n  ((B)))", ErrorKind::ExpectedLabel, 2, 4),
+            ("(A \"B)", ErrorKind::UnclosedString, 1, 4),
+            ("(A \"\\x\")", ErrorKind::InvalidEscape('x'), 1, 5),
+            ("(A B {k v})", ErrorKind::MisplacedAttrs, 1, 6),
+            ("(A {k})", ErrorKind::ExpectedAttr, 1, 6),
+            ("(A {k v", ErrorKind::UnclosedAttrs, 1, 4),
+        ];
+        for (text, kind, line, column) in cases {
+            let err = parse(text).unwrap_err();
+            assert_eq!(err.kind, kind, "{text:?}");
+            assert_eq!(
+                (err.position.line, err.position.column),
+                (line, column),
+                "{text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn sexpr_to_string_test1() {
+        assert_eq!(to_string(&example()), "(A (B D E) C)");
+        assert_eq!(to_string_with_width(&example(), 10), "(A\n  (B D E)\n  C)");
+        assert_eq!(
+            to_string_with_width(&example(), 6),
+            "(A\n  (B\n    D\n    E)\n  C)"
+        );
+    }
+
+    #[test]
+    fn sexpr_attrs_test1() {
+        let mut tree = example();
+        tree.attrs.insert("note".to_owned(), "a b".to_owned());
+        tree.kids[1]
+            .attrs
+            .insert("color".to_owned(), "red".to_owned());
+
+        assert_eq!(
+            to_string(&tree),
+            r#"(A {note "a b"} (B D E) (C {color red}))"#
+        );
+        assert_eq!(
+            to_string_with_width(&tree, 20),
+            "(A {note \"a b\"}\n  (B D E)\n  (C {color red}))"
+        );
+        for width in [0, 20, DEFAULT_WIDTH] {
+            assert_eq!(parse(&to_string_with_width(&tree, width)).unwrap(), tree);
+        }
+    }
+
+    #[test]
+    fn sexpr_round_trip_test1() {
+        let trees = [
+            example(),
+            Tree::mk("a b", &[Tree::mk("", &[]), Tree::mk("\"\\\n\t;", &[])]),
+            crate::tree::big_tree(3, 4),
+        ];
+        for tree in trees {
+            for width in [0, 10, DEFAULT_WIDTH] {
+                assert_eq!(parse(&to_string_with_width(&tree, width)).unwrap(), tree);
+            }
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use super::{Attrs, Error, Index, NodeId, Path, Step, Tooth, Tree};
+
+/// A [`Tree`] opened up at one of its nodes, so that the node can be moved
+/// away from and edited without walking down from the root each time.
+///
+/// `focus` is the subtree under the cursor, and the path above it is kept as
+/// the nodes that it was taken out of, outermost first. Moving up, down, left
+/// or right takes constant time, whatever the depth of the focus and the
+/// number of its siblings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Zipper<L = String> {
+    path: Vec<Frame<L>>,
+    pub focus: Tree<L>,
+}
+
+/// A node on the path to the focus of a [`Zipper`], with a placeholder in
+/// place of its kid that the focus is in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Frame<L> {
+    label: L,
+    kids: Vec<Tree<L>>,
+    /// The step to the kid that the focus is in.
+    step: Step,
+    attrs: Attrs,
+    folded: bool,
+    id: Option<NodeId>,
+}
+
+impl<L> Zipper<L> {
+    /// A zipper focused on the root of `tree`.
+    pub fn new(tree: Tree<L>) -> Self {
+        Self {
+            path: vec![],
+            focus: tree,
+        }
+    }
+
+    /// A zipper focused on the node of `tree` at `index`.
+    pub fn from_tree_at_index(tree: Tree<L>, index: &Index) -> Result<Self, Error>
+    where
+        L: Default,
+    {
+        let mut zipper = Self::new(tree);
+        for step in index {
+            zipper.move_down(*step)?;
+        }
+        Ok(zipper)
+    }
+
+    /// Closes the zipper, returning the whole tree and the index of the focus.
+    pub fn into_tree_and_index(self) -> (Tree<L>, Index) {
+        let index = self.index();
+        (self.unzip(), index)
+    }
+
+    /// Closes the zipper back up into the whole tree.
+    pub fn unzip(self) -> Tree<L> {
+        let Self { path, focus } = self;
+        path.into_iter()
+            .rev()
+            .fold(focus, |focus, frame| frame.wrap(focus))
+    }
+
+    /// Splits the zipper into the path to the focus, in the order that
+    /// [`Tree::wrap_with_path`] expects, and the focus.
+    pub fn into_path_and_focus(self) -> (Path<L>, Tree<L>) {
+        let path = self.path.into_iter().map(Frame::into_tooth).collect();
+        (path, self.focus)
+    }
+
+    /// The index of the focus in the whole tree.
+    pub fn index(&self) -> Index {
+        Index(self.path.iter().map(|frame| frame.step).collect())
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// Moving up or down leaves a node with a default label behind for a
+    /// moment, in place of the focus, hence `L: Default`.
+    pub fn move_up(&mut self) -> Result<(), Error>
+    where
+        L: Default,
+    {
+        let frame = self.path.pop().ok_or(Error::AtRoot)?;
+        let focus = std::mem::take(&mut self.focus);
+        self.focus = frame.wrap(focus);
+        Ok(())
+    }
+
+    pub fn move_down(&mut self, step: Step) -> Result<(), Error>
+    where
+        L: Default,
+    {
+        if step >= self.focus.kids.len() {
+            let mut index = self.index();
+            index.push(step);
+            return Err(Error::OutOfBounds {
+                depth: self.path.len(),
+                index,
+                len: self.focus.kids.len(),
+            });
+        }
+        let mut kids = std::mem::take(&mut self.focus.kids);
+        let kid = std::mem::take(&mut kids[step]);
+        self.path.push(Frame {
+            label: std::mem::take(&mut self.focus.label),
+            kids,
+            step,
+            attrs: std::mem::take(&mut self.focus.attrs),
+            folded: self.focus.folded,
+            id: self.focus.id,
+        });
+        self.focus = kid;
+        Ok(())
+    }
+
+    pub fn move_left(&mut self) -> Result<(), Error> {
+        let frame = self.path.last_mut().ok_or(Error::AtRoot)?;
+        let step = frame.step.checked_sub(1).ok_or(Error::NoSibling)?;
+        frame.move_to(step, &mut self.focus);
+        Ok(())
+    }
+
+    pub fn move_right(&mut self) -> Result<(), Error> {
+        let frame = self.path.last_mut().ok_or(Error::AtRoot)?;
+        let step = frame.step + 1;
+        if step >= frame.kids.len() {
+            return Err(Error::NoSibling);
+        }
+        frame.move_to(step, &mut self.focus);
+        Ok(())
+    }
+}
+
+impl<L> Frame<L> {
+    /// Puts `focus` back in its place, and takes the kid at `step` out as the
+    /// new focus.
+    fn move_to(&mut self, step: Step, focus: &mut Tree<L>) {
+        std::mem::swap(&mut self.kids[self.step], focus);
+        std::mem::swap(&mut self.kids[step], focus);
+        self.step = step;
+    }
+
+    /// The node, with `focus` put back in its place.
+    fn wrap(mut self, focus: Tree<L>) -> Tree<L> {
+        self.kids[self.step] = focus;
+        Tree {
+            label: self.label,
+            kids: self.kids,
+            attrs: self.attrs,
+            folded: self.folded,
+            id: self.id,
+        }
+    }
+
+    fn into_tooth(mut self) -> Tooth<L> {
+        let kids_right = self.kids.split_off(self.step + 1);
+        self.kids.pop();
+        Tooth {
+            label: self.label,
+            kids_left: self.kids,
+            kids_right,
+            attrs: self.attrs,
+            folded: self.folded,
+            id: self.id,
+        }
+    }
+}
+
+impl<L> From<Tree<L>> for Zipper<L> {
+    fn from(tree: Tree<L>) -> Self {
+        Self::new(tree)
+    }
+}
+
+impl<L> From<Zipper<L>> for Tree<L> {
+    fn from(zipper: Zipper<L>) -> Self {
+        zipper.unzip()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::example;
+
+    #[test]
+    fn zipper_round_trip_test1() {
+        let tree = example();
+        let index = Index(vec![0, 1]);
+
+        let zipper = Zipper::from_tree_at_index(tree.clone(), &index).unwrap();
+        assert_eq!(zipper.focus, Tree::mk("E", &[]));
+        assert_eq!(zipper.index(), index);
+
+        assert_eq!(zipper.into_tree_and_index(), (tree, index));
+    }
+
+    #[test]
+    fn zipper_move_test1() {
+        let tree = example();
+        let mut zipper = Zipper::new(tree.clone());
+
+        zipper.move_down(0).unwrap();
+        zipper.move_down(0).unwrap();
+        assert_eq!(zipper.focus.label, "D");
+        assert_eq!(zipper.move_left(), Err(Error::NoSibling));
+
+        zipper.move_right().unwrap();
+        assert_eq!(zipper.focus.label, "E");
+        assert_eq!(zipper.index(), Index(vec![0, 1]));
+        assert_eq!(zipper.move_right(), Err(Error::NoSibling));
+        assert_eq!(
+            zipper.move_down(0),
+            Err(Error::OutOfBounds {
+                index: Index(vec![0, 1, 0]),
+                depth: 2,
+                len: 0
+            })
+        );
+
+        zipper.move_up().unwrap();
+        zipper.move_right().unwrap();
+        assert_eq!(zipper.focus.label, "C");
+        zipper.move_left().unwrap();
+        assert_eq!(zipper.focus.label, "B");
+
+        zipper.move_up().unwrap();
+        assert!(zipper.is_root());
+        assert_eq!(zipper.move_up(), Err(Error::AtRoot));
+        assert_eq!(zipper.unzip(), tree);
+    }
+
+    #[test]
+    fn zipper_move_test2() {
+        let kids: Vec<Tree> = (0..1000).map(|i| Tree::mk(&i.to_string(), &[])).collect();
+        let tree = Tree::mk("A", &kids);
+        let mut zipper = Zipper::from_tree_at_index(tree.clone(), &Index(vec![500])).unwrap();
+
+        for i in 501..1000 {
+            zipper.move_right().unwrap();
+            assert_eq!(zipper.focus.label, i.to_string());
+        }
+        assert_eq!(zipper.move_right(), Err(Error::NoSibling));
+        for i in (0..999).rev() {
+            zipper.move_left().unwrap();
+            assert_eq!(zipper.focus.label, i.to_string());
+        }
+        assert_eq!(zipper.move_left(), Err(Error::NoSibling));
+        assert_eq!(zipper.index(), Index(vec![0]));
+
+        zipper.move_right().unwrap();
+        let (path, focus) = zipper.clone().into_path_and_focus();
+        assert_eq!(path[0].kids_right[0].label, "2");
+        assert_eq!(focus.label, "1");
+        assert_eq!(zipper.unzip(), tree);
+    }
+
+    #[test]
+    fn zipper_from_tree_at_index_test1() {
+        assert_eq!(
+            Zipper::from_tree_at_index(example(), &Index(vec![1, 0])),
+            Err(Error::OutOfBounds {
+                index: Index(vec![1, 0]),
+                depth: 1,
+                len: 0
+            })
+        );
+    }
+}